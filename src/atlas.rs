@@ -0,0 +1,265 @@
+use gl;
+use gl::types::*;
+use image::RgbaImage;
+
+//Big enough for the tallest question widget, a few slide images and a lot of glyphs. Smaller GPUs get what they can do.
+pub const MAX_ATLAS_SIZE: u32 = 4096;
+//Left empty around everything so filtering doesn't pick up the neighbours.
+const GAP: u32 = 1;
+
+//Where something is in the atlas, in pixels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn offset(&self) -> (f32, f32) {
+        (self.x as f32, self.y as f32)
+    }
+}
+
+//A row of the atlas. Things are put in left to right and the row is as tall as the first thing in it.
+struct Shelf {
+    y: u32,
+    height: u32,
+    next_x: u32,
+}
+
+//The one texture everything is drawn from. Images are packed into shelves and only the rows that changed are uploaded.
+//Nothing is taken out on its own. Once it is full it gets cleared, and generation() changes so users know to put back what they still need.
+pub struct TextureAtlas {
+    image: RgbaImage,
+    shelves: Vec<Shelf>,
+    generation: u32,
+    //The rows changed since the last upload.
+    dirty: Option<(u32, u32)>,
+}
+
+impl TextureAtlas {
+    //Square, `size` pixels across.
+    pub fn new(size: u32) -> TextureAtlas {
+        TextureAtlas {
+            image: RgbaImage::new(size, size),
+            shelves: Vec::new(),
+            generation: 0,
+            dirty: Some((0, size)),
+        }
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        (self.image.width() as f32, self.image.height() as f32)
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    //Whether something this big could ever fit, even in an empty atlas.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        width + GAP <= self.image.width() && height + GAP <= self.image.height()
+    }
+
+    //None if there is no room left.
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<Region> {
+        if !self.fits(width, height) {
+            return None;
+        }
+        let (padded_width, padded_height) = (width + GAP, height + GAP);
+        let size = self.image.width();
+
+        //The shortest shelf it fits on wastes the least space.
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|s| s.height >= padded_height && s.next_x + padded_width <= size)
+            .min_by_key(|s| s.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self.shelves.last().map_or(0, |s| s.y + s.height);
+                if y + padded_height > size {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    next_x: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let region = Region {
+            x: shelf.next_x,
+            y: shelf.y,
+            width,
+            height,
+        };
+        shelf.next_x += padded_width;
+        Some(region)
+    }
+
+    pub fn insert(&mut self, image: &RgbaImage) -> Option<Region> {
+        let region = self.allocate(image.width(), image.height())?;
+        self.update(region, |texture| {
+            for (x, y, pixel) in image.enumerate_pixels() {
+                texture.put_pixel(region.x + x, region.y + y, *pixel);
+            }
+        });
+        Some(region)
+    }
+
+    //For drawing into a region that was allocated earlier. Only the region should be drawn to.
+    pub fn update<F: FnOnce(&mut RgbaImage)>(&mut self, region: Region, draw: F) {
+        draw(&mut self.image);
+        let rows = (region.y, region.y + region.height);
+        self.dirty = Some(match self.dirty {
+            Some((top, bottom)) => (top.min(rows.0), bottom.max(rows.1)),
+            None => rows,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        for pixel in self.image.pixels_mut() {
+            pixel.data = [0, 0, 0, 0];
+        }
+        self.shelves.clear();
+        self.generation += 1;
+        self.dirty = Some((0, self.image.height()));
+    }
+
+    //Sends the changed rows to the bound texture, which has to have been made with create_texture.
+    pub unsafe fn upload(&mut self) {
+        let (top, bottom) = match self.dirty.take() {
+            Some(rows) => rows,
+            None => return,
+        };
+        let row_bytes = self.image.width() as usize * 4;
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            top as GLint,
+            self.image.width() as GLsizei,
+            (bottom - top) as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            self.image.as_ptr().add(top as usize * row_bytes) as *const std::os::raw::c_void,
+        );
+    }
+
+    //Sets up storage for the whole atlas in the bound texture.
+    pub unsafe fn create_texture(&mut self) {
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as GLint,
+            self.image.width() as GLint,
+            self.image.height() as GLint,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            self.image.as_ptr() as *const std::os::raw::c_void,
+        );
+        self.dirty = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn overlaps(a: &Region, b: &Region) -> bool {
+        a.x < b.x + b.width + GAP
+            && b.x < a.x + a.width + GAP
+            && a.y < b.y + b.height + GAP
+            && b.y < a.y + a.height + GAP
+    }
+
+    #[test]
+    fn allocate_packs_shelves_without_overlap() {
+        let mut atlas = TextureAtlas::new(64);
+        let sizes = [
+            (10, 10),
+            (20, 5),
+            (5, 30),
+            (30, 10),
+            (10, 10),
+            (40, 20),
+            (4, 4),
+        ];
+        let regions: Vec<Region> = sizes
+            .iter()
+            .map(|&(w, h)| atlas.allocate(w, h).unwrap())
+            .collect();
+
+        for (i, a) in regions.iter().enumerate() {
+            assert_eq!((a.width, a.height), sizes[i]);
+            assert!(a.x + a.width + GAP <= 64 && a.y + a.height + GAP <= 64);
+            for b in regions[i + 1..].iter() {
+                assert!(!overlaps(a, b), "{:?} and {:?} overlap", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn allocate_picks_the_shortest_shelf_with_room() {
+        let mut atlas = TextureAtlas::new(64);
+        let tall = atlas.allocate(40, 30).unwrap();
+        let short = atlas.allocate(40, 10).unwrap();
+        assert_eq!(short.y, tall.height + GAP);
+        assert_eq!(atlas.allocate(10, 5).unwrap().y, short.y);
+        assert_eq!(atlas.allocate(10, 20).unwrap().y, tall.y);
+    }
+
+    #[test]
+    fn allocate_fails_when_full_until_cleared() {
+        let mut atlas = TextureAtlas::new(32);
+        assert!(!atlas.fits(32, 1));
+        assert_eq!(atlas.allocate(32, 1), None);
+
+        while atlas.allocate(15, 15).is_some() {}
+        assert_eq!(atlas.allocate(15, 15), None);
+        assert_eq!(atlas.generation(), 0);
+
+        atlas.clear();
+        assert_eq!(atlas.generation(), 1);
+        assert_eq!(
+            atlas.allocate(15, 15),
+            Some(Region {
+                x: 0,
+                y: 0,
+                width: 15,
+                height: 15
+            })
+        );
+    }
+
+    #[test]
+    fn insert_copies_the_image_and_marks_its_rows() {
+        let mut atlas = TextureAtlas::new(16);
+        atlas.dirty = None;
+        atlas.allocate(3, 3).unwrap();
+        let image = RgbaImage::from_pixel(2, 2, Rgba { data: [1, 2, 3, 4] });
+        let region = atlas.insert(&image).unwrap();
+
+        assert_eq!(
+            atlas.image.get_pixel(region.x + 1, region.y + 1).data,
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            atlas.image.get_pixel(region.x + 2, region.y).data,
+            [0, 0, 0, 0]
+        );
+        assert_eq!(atlas.dirty, Some((region.y, region.y + 2)));
+
+        atlas.clear();
+        assert_eq!(atlas.image.get_pixel(region.x, region.y).data, [0, 0, 0, 0]);
+        assert_eq!(atlas.dirty, Some((0, 16)));
+    }
+}
@@ -0,0 +1,305 @@
+use deck::{read_header, read_slides, Header, Slide};
+use error::{Location, QuizLoadError};
+use format::{
+    deck_choices, question_location, read_deck_file, read_structured_slides, slide_location,
+    DeckFormat,
+};
+use question::{distinct_answers, DEFAULT_CHOICES};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    Unreadable,
+    MissingImage,
+    BadImage,
+    EmptyDeck,
+    BadHeader,
+    MissingAnswers,
+    ExtraColumns,
+    EmptyAnswer,
+    DuplicateSlide,
+    TooFewAnswers,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub file: PathBuf,
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl From<QuizLoadError> for Problem {
+    fn from(error: QuizLoadError) -> Problem {
+        let kind = match error {
+            QuizLoadError::MissingImage { .. } => ProblemKind::MissingImage,
+            QuizLoadError::BadImage { .. } => ProblemKind::BadImage,
+            QuizLoadError::EmptyDeck { .. } => ProblemKind::EmptyDeck,
+            QuizLoadError::BadHeader { .. } => ProblemKind::BadHeader,
+            _ => ProblemKind::Unreadable,
+        };
+        let location = match error {
+            QuizLoadError::Io { ref file, .. } => Location {
+                file: file.clone(),
+                line: 0,
+                column: 0,
+                slide: None,
+            },
+            _ => error.location().unwrap().clone(),
+        };
+        Problem {
+            kind,
+            location,
+            message: error.message(),
+        }
+    }
+}
+
+//Loads the quiz and reports everything that would stop it loading or make a question impossible to ask.
+//`choices` overrides the number the deck asks for, if it asks for one.
+pub fn check_quiz(file: &Path, choices: Option<usize>) -> Report {
+    let mut report = Report {
+        file: file.to_path_buf(),
+        problems: Vec::new(),
+    };
+
+    let loaded = match DeckFormat::from_path(file) {
+        DeckFormat::Csv => read_csv(file, &mut report.problems).map(|(h, s)| (h, s, None)),
+        format => read_structured(file, format, &mut report.problems),
+    };
+    let (header, slides, deck_choices) = match loaded {
+        Some(loaded) => loaded,
+        None => return report,
+    };
+    let choices = choices.or(deck_choices).unwrap_or(DEFAULT_CHOICES);
+
+    check_columns(&header, &slides, file, &mut report.problems);
+    check_duplicates(&slides, file, &mut report.problems);
+    check_distinct_answers(&header, &slides, file, choices, &mut report.problems);
+
+    report
+}
+
+//None if the file couldn't be read far enough to get any slides.
+fn read_csv(file: &Path, problems: &mut Vec<Problem>) -> Option<(Header, Vec<Slide>)> {
+    //Flexible so short and long rows are reported here instead of as CSV errors.
+    let mut csv_reader = match File::open(file) {
+        Ok(f) => csv::ReaderBuilder::new().flexible(true).from_reader(f),
+        Err(error) => {
+            problems.push(Problem::from(QuizLoadError::Io {
+                file: file.to_path_buf(),
+                error,
+            }));
+            return None;
+        }
+    };
+    let header = match read_header(&mut csv_reader, file) {
+        Ok(header) => header,
+        Err(error) => {
+            problems.push(Problem::from(error));
+            return None;
+        }
+    };
+
+    let (slides, errors) = read_slides(&mut csv_reader, &header, file, true);
+    problems.extend(errors.into_iter().map(Problem::from));
+    Some((header, slides))
+}
+
+fn read_structured(
+    file: &Path,
+    format: DeckFormat,
+    problems: &mut Vec<Problem>,
+) -> Option<(Header, Vec<Slide>, Option<usize>)> {
+    let deck = match read_deck_file(file, format) {
+        Ok(deck) => deck,
+        Err(error) => {
+            problems.push(Problem::from(error));
+            return None;
+        }
+    };
+    let choices = match deck_choices(&deck, file) {
+        Ok(choices) => choices,
+        Err(error) => {
+            problems.push(Problem::from(error));
+            None
+        }
+    };
+
+    let (header, slides, errors) = read_structured_slides(&deck, file, true);
+    problems.extend(errors.into_iter().map(Problem::from));
+    Some((header, slides, choices))
+}
+
+fn check_columns(header: &Header, slides: &[Slide], file: &Path, problems: &mut Vec<Problem>) {
+    for slide in slides.iter() {
+        let location = |column: usize| slide_location(file, slide.line, column);
+
+        if slide.answers.len() < header.questions.len() {
+            problems.push(Problem {
+                kind: ProblemKind::MissingAnswers,
+                location: location(header.question_column(slide.answers.len())),
+                message: format!(
+                    "expected {} answers but found {}",
+                    header.questions.len(),
+                    slide.answers.len()
+                ),
+            });
+        } else if slide.answers.len() > header.questions.len() {
+            problems.push(Problem {
+                kind: ProblemKind::ExtraColumns,
+                location: location(header.question_column(header.questions.len())),
+                message: format!(
+                    "{} column(s) past the last question",
+                    slide.answers.len() - header.questions.len()
+                ),
+            });
+        }
+
+        for (category, answer) in slide.answers.iter().enumerate() {
+            if category < header.questions.len() && answer.trim().is_empty() {
+                problems.push(Problem {
+                    kind: ProblemKind::EmptyAnswer,
+                    location: location(header.question_column(category)),
+                    message: format!("empty answer for \"{}\"", header.questions[category]),
+                });
+            }
+        }
+    }
+}
+
+fn check_duplicates(slides: &[Slide], file: &Path, problems: &mut Vec<Problem>) {
+    let mut seen = HashMap::new();
+    let numbered_by = match DeckFormat::from_path(file) {
+        DeckFormat::Csv => "line",
+        _ => "slide",
+    };
+
+    for slide in slides.iter() {
+        let key = (&slide.image_path, &slide.hints, &slide.answers);
+        match seen.get(&key) {
+            Some(first_line) => problems.push(Problem {
+                kind: ProblemKind::DuplicateSlide,
+                location: slide_location(file, slide.line, 1),
+                message: format!("same slide as {} {}", numbered_by, first_line),
+            }),
+            None => {
+                seen.insert(key, slide.line);
+            }
+        }
+    }
+}
+
+fn check_distinct_answers(
+    header: &Header,
+    slides: &[Slide],
+    file: &Path,
+    choices: usize,
+    problems: &mut Vec<Problem>,
+) {
+    for (category, question) in header.questions.iter().enumerate() {
+        let answers = distinct_answers(category, &[], slides);
+
+        //Anything less and generate_question has to leave choices out.
+        if answers.len() < choices {
+            problems.push(Problem {
+                kind: ProblemKind::TooFewAnswers,
+                location: question_location(file, header.question_column(category)),
+                message: format!(
+                    "\"{}\" has {} different answers, so questions will have fewer than {} choices",
+                    question,
+                    answers.len(),
+                    choices
+                ),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    //Named after the process so test runs at the same time don't share files.
+    fn write_deck(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("rusty_quiz_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn found(report: &Report) -> Vec<(ProblemKind, u64, usize)> {
+        report
+            .problems
+            .iter()
+            .map(|p| (p.kind, p.location.line, p.location.column))
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_problem_where_it_is() {
+        let file = write_deck(
+            "problems.csv",
+            "hint,Capital,Currency\n\
+             France,Paris,Euro\n\
+             Germany,Berlin\n\
+             Spain,,Euro\n\
+             France,Paris,Euro\n\
+             Italy,Rome,Euro,Lira\n",
+        );
+        let report = check_quiz(&file, Some(3));
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(
+            found(&report),
+            vec![
+                (ProblemKind::MissingAnswers, 3, 3),
+                (ProblemKind::EmptyAnswer, 4, 2),
+                (ProblemKind::ExtraColumns, 6, 4),
+                (ProblemKind::DuplicateSlide, 5, 1),
+                (ProblemKind::TooFewAnswers, 1, 3),
+            ]
+        );
+        assert_eq!(report.problems[3].message, "same slide as line 2");
+    }
+
+    #[test]
+    fn reports_files_that_stop_loading() {
+        let file = write_deck("image.csv", "image,Capital\nnot there.png,Paris\n");
+        let report = check_quiz(&file, Some(2));
+        fs::remove_file(&file).unwrap();
+        assert_eq!(found(&report)[0], (ProblemKind::MissingImage, 2, 1));
+
+        let file = write_deck("header.csv", "image,image,Capital\n");
+        let report = check_quiz(&file, None);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(found(&report), vec![(ProblemKind::BadHeader, 1, 2)]);
+
+        let missing = env::temp_dir().join("rusty_quiz_check_no_such_deck.csv");
+        let report = check_quiz(&missing, None);
+        assert_eq!(report.problems[0].kind, ProblemKind::Unreadable);
+        assert!(!report.is_ok());
+    }
+}
@@ -0,0 +1,514 @@
+use error::{Location, QuizLoadError};
+use format::{deck_choices, read_deck_file, read_structured_slides, DeckFormat};
+use image::RgbaImage;
+use question::{AnswerMode, CrossCategories, DEFAULT_CHOICES};
+use schedule::slide_keys;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Separates the other accepted spellings of an answer, as in "USA|United States".
+pub const ALIAS_SEPARATOR: char = '|';
+
+//What a column of a CSV quiz holds, going by its name in the header.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Column {
+    Image,
+    //Index into Slide::hints.
+    Hint(usize),
+    //Index into Header::questions and Slide::answers.
+    Question(usize),
+    //From "explain:Question" columns. Index into Header::questions and Slide::explanations.
+    Explanation(usize),
+    //Columns whose name starts with '#', for notes that aren't part of the quiz.
+    Ignored,
+}
+
+pub struct Header {
+    pub number_of_hints: i32,
+    pub questions: Vec<String>,
+    //From "hint:label" columns. None for plain "hint" columns.
+    pub hint_labels: Vec<Option<String>>,
+    //Every column in file order.
+    pub columns: Vec<Column>,
+}
+
+impl Header {
+    //The usual layout of an image, the hints and then the questions, for decks that don't come from CSV.
+    pub fn new(hint_labels: Vec<Option<String>>, questions: Vec<String>) -> Header {
+        let mut columns = vec![Column::Image];
+        columns.extend((0..hint_labels.len()).map(Column::Hint));
+        columns.extend((0..questions.len()).map(Column::Question));
+        Header {
+            number_of_hints: hint_labels.len() as i32,
+            questions,
+            hint_labels,
+            columns,
+        }
+    }
+
+    //Finds a question by name, ignoring case.
+    pub fn category(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.questions.iter().position(|q| q.to_lowercase() == name)
+    }
+
+    //The column a question's answers are in, starting at 1.
+    //Categories past the last question count on from the end of the header, where extra columns in a row end up.
+    pub fn question_column(&self, category: usize) -> usize {
+        let found = self
+            .columns
+            .iter()
+            .position(|c| *c == Column::Question(category));
+        match found {
+            Some(i) => i + 1,
+            None => self.columns.len() + category.saturating_sub(self.questions.len()) + 1,
+        }
+    }
+
+    //The non-empty hints of a slide ready to show, with their labels in front.
+    pub fn hint_text(&self, slide: &Slide) -> Vec<String> {
+        slide
+            .hints
+            .iter()
+            .enumerate()
+            .filter(|&(_, h)| !h.is_empty())
+            .map(|(i, h)| match self.hint_labels.get(i) {
+                Some(Some(label)) => format!("{}: {}", label, h),
+                _ => h.clone(),
+            })
+            .collect()
+    }
+}
+
+//Information about the deck itself. Only JSON and TOML decks have somewhere to put it.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.author.is_none()
+    }
+}
+
+pub struct Slide {
+    //Line of the quiz file the slide was read from. JSON and TOML decks number their slides from 1 instead.
+    pub line: u64,
+    //As written in the quiz file. See resolve_image_path for where the image is loaded from.
+    pub image_path: Option<PathBuf>,
+    pub image: Option<RgbaImage>,
+    pub hints: Vec<String>,
+    //The canonical answer for each question, which is what multiple choice shows.
+    pub answers: Vec<String>,
+    //Other spellings of answers[i] that typed answers accept.
+    pub aliases: Vec<Vec<String>>,
+    //Why answers[i] is right. Empty if the deck doesn't say.
+    pub explanations: Vec<String>,
+}
+
+impl Slide {
+    //The canonical answer followed by its aliases.
+    pub fn accepted_answers(&self, category: usize) -> Vec<String> {
+        let mut accepted: Vec<String> = self.answers.get(category).into_iter().cloned().collect();
+        if let Some(aliases) = self.aliases.get(category) {
+            accepted.extend(aliases.iter().cloned());
+        }
+        accepted
+    }
+}
+
+//A whole deck: the header describing the columns and every slide in file order.
+pub struct Quiz {
+    pub header: Header,
+    pub slides: Vec<Slide>,
+    pub metadata: Metadata,
+    //How many answers each question offers, between MIN_CHOICES and MAX_CHOICES.
+    pub choices: usize,
+    pub mode: AnswerMode,
+    //Replaces showing the slide with one of its answers. Reverse questions don't use it.
+    pub cross: Option<CrossCategories>,
+    //Typos allowed in a typed answer. None allows DEFAULT_TOLERANCE, and fewer for short answers.
+    pub tolerance: Option<usize>,
+    //What the progress and results files call each slide, from slide_keys.
+    pub slide_keys: Vec<String>,
+}
+
+impl Quiz {
+    //Stops at the first problem found.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Quiz, QuizLoadError> {
+        Quiz::load(path.as_ref(), false).map_err(|mut errors| errors.remove(0))
+    }
+
+    //Keeps going after a bad row so every problem in the file is reported at once.
+    pub fn from_path_all_errors<P: AsRef<Path>>(path: P) -> Result<Quiz, Vec<QuizLoadError>> {
+        Quiz::load(path.as_ref(), true)
+    }
+
+    //The format is picked by the file extension.
+    fn load(file: &Path, collect_errors: bool) -> Result<Quiz, Vec<QuizLoadError>> {
+        let (header, slides, errors, metadata, choices) = match DeckFormat::from_path(file) {
+            DeckFormat::Csv => {
+                let mut csv_reader = open_quiz_file(file).map_err(|e| vec![e])?;
+                let header = read_header(&mut csv_reader, file).map_err(|e| vec![e])?;
+                let (slides, errors) = read_slides(&mut csv_reader, &header, file, collect_errors);
+                (header, slides, errors, Metadata::default(), None)
+            }
+            format => {
+                let deck = read_deck_file(file, format).map_err(|e| vec![e])?;
+                let choices = deck_choices(&deck, file).map_err(|e| vec![e])?;
+                let (header, slides, errors) = read_structured_slides(&deck, file, collect_errors);
+                (header, slides, errors, deck.metadata, choices)
+            }
+        };
+
+        if errors.is_empty() {
+            Ok(Quiz {
+                header,
+                metadata,
+                choices: choices.unwrap_or(DEFAULT_CHOICES),
+                mode: AnswerMode::MultipleChoice,
+                cross: None,
+                tolerance: None,
+                slide_keys: slide_keys(&slides),
+                slides,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+pub fn open_quiz_file(file: &Path) -> Result<csv::Reader<File>, QuizLoadError> {
+    match File::open(file) {
+        Ok(f) => Ok(csv::Reader::from_reader(f)),
+        Err(error) => Err(QuizLoadError::Io {
+            file: file.to_path_buf(),
+            error,
+        }),
+    }
+}
+
+fn csv_error(file: &Path, error: csv::Error) -> QuizLoadError {
+    let location = |pos: &Option<csv::Position>, column: usize| Location {
+        file: file.to_path_buf(),
+        line: pos.as_ref().map(|p| p.line()).unwrap_or(0),
+        column,
+        slide: None,
+    };
+
+    let location = match *error.kind() {
+        csv::ErrorKind::UnequalLengths {
+            ref pos,
+            expected_len,
+            len,
+        } => {
+            return QuizLoadError::ColumnCount {
+                location: location(pos, expected_len.min(len) as usize + 1),
+                expected: expected_len as usize,
+                found: len as usize,
+            }
+        }
+        csv::ErrorKind::Utf8 { ref pos, ref err } => location(pos, err.field() + 1),
+        csv::ErrorKind::Deserialize { ref pos, ref err } => {
+            location(pos, err.field().map(|f| f as usize + 1).unwrap_or(1))
+        }
+        _ => location(&None, 0),
+    };
+    QuizLoadError::Csv { location, error }
+}
+
+//Relative image paths start from the directory the quiz file is in, not the working directory.
+pub fn resolve_image_path(file: &Path, value: &str) -> PathBuf {
+    let path = Path::new(value);
+    match file.parent() {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub fn read_image(location: Location, path: &Path) -> Result<RgbaImage, QuizLoadError> {
+    if !path.exists() {
+        return Err(QuizLoadError::MissingImage {
+            location,
+            path: path.to_path_buf(),
+        });
+    }
+    match image::open(path) {
+        Ok(image) => Ok(image.to_rgba()),
+        Err(error) => Err(QuizLoadError::BadImage {
+            location,
+            path: path.to_path_buf(),
+            error,
+        }),
+    }
+}
+
+//The first part of the cell is the canonical answer and the rest are aliases. Empty aliases are dropped.
+pub fn split_answer(cell: &str) -> (String, Vec<String>) {
+    let mut parts = cell.split(ALIAS_SEPARATOR).map(|p| p.trim().to_string());
+    let canonical = parts.next().unwrap_or_default();
+    (canonical, parts.filter(|p| !p.is_empty()).collect())
+}
+
+fn read_slide(
+    record: &csv::StringRecord,
+    header: &Header,
+    file: &Path,
+) -> Result<Slide, QuizLoadError> {
+    let line = record.position().map(|p| p.line()).unwrap_or(0);
+
+    let mut slide = Slide {
+        line,
+        image_path: None,
+        image: None,
+        hints: Vec::new(),
+        answers: Vec::new(),
+        aliases: Vec::new(),
+        explanations: vec![String::new(); header.questions.len()],
+    };
+
+    //Cells are trimmed, so one with only spaces in it counts as empty.
+    for (i, value) in record.iter().enumerate() {
+        let value = value.trim().to_string();
+        match header.columns.get(i) {
+            Some(Column::Image) => {
+                if !value.is_empty() {
+                    let location = Location {
+                        file: file.to_path_buf(),
+                        line,
+                        column: i + 1,
+                        slide: None,
+                    };
+                    slide.image = Some(read_image(location, &resolve_image_path(file, &value))?);
+                    slide.image_path = Some(PathBuf::from(value));
+                }
+            }
+            Some(Column::Hint(_)) => {
+                slide.hints.push(value);
+            }
+            Some(&Column::Explanation(category)) => {
+                slide.explanations[category] = value;
+            }
+            Some(Column::Ignored) => (),
+            //Columns past the end of the header only get here when the reader is flexible, and check_quiz reports them as extra answers.
+            Some(Column::Question(_)) | None => {
+                let (canonical, aliases) = split_answer(&value);
+                slide.answers.push(canonical);
+                slide.aliases.push(aliases);
+            }
+        }
+    }
+
+    Ok(slide)
+}
+
+//A row that fails to load is left out of the returned slides.
+pub fn read_slides<R: io::Read>(
+    csv_reader: &mut csv::Reader<R>,
+    header: &Header,
+    file: &Path,
+    collect_errors: bool,
+) -> (Vec<Slide>, Vec<QuizLoadError>) {
+    let mut slides: Vec<Slide> = Vec::new();
+    let mut errors: Vec<QuizLoadError> = Vec::new();
+
+    for record in csv_reader.records() {
+        //An I/O error means the rest of the file can't be read, so there is nothing more to collect.
+        let fatal = match record {
+            Err(ref e) => e.is_io_error(),
+            Ok(_) => false,
+        };
+
+        match record
+            .map_err(|e| csv_error(file, e))
+            .and_then(|record| read_slide(&record, header, file))
+        {
+            Ok(slide) => slides.push(slide),
+            Err(error) => {
+                errors.push(error);
+                if !collect_errors || fatal {
+                    break;
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() && slides.is_empty() {
+        errors.push(QuizLoadError::EmptyDeck {
+            location: Location {
+                file: file.to_path_buf(),
+                line: csv_reader.position().line(),
+                column: 1,
+                slide: None,
+            },
+        });
+    }
+
+    (slides, errors)
+}
+
+fn header_error(file: &Path, column_index: usize, message: String) -> QuizLoadError {
+    QuizLoadError::BadHeader {
+        location: Location {
+            file: file.to_path_buf(),
+            line: 1,
+            column: column_index + 1,
+            slide: None,
+        },
+        message,
+    }
+}
+
+pub fn read_header<R: io::Read>(
+    csv_reader: &mut csv::Reader<R>,
+    file: &Path,
+) -> Result<Header, QuizLoadError> {
+    let mut hint_labels: Vec<Option<String>> = Vec::new();
+    let mut questions: Vec<String> = Vec::new();
+    let mut columns: Vec<Column> = Vec::new();
+    //Explanations can come before their question, so they are matched up once every column is known.
+    let mut explanations: Vec<(usize, String)> = Vec::new();
+
+    //Every column gets its role from its name, so they can be in any order.
+    //Names ignore case, a name starting with '#' is ignored and anything that isn't "image", "hint" or "explain" is a question.
+    let header_record = csv_reader.headers().map_err(|e| csv_error(file, e))?;
+    for (i, name) in header_record.iter().enumerate() {
+        let error = |message: String| header_error(file, i, message);
+        let name = name.trim();
+        let lowercase = name.to_lowercase();
+
+        let column = if name.starts_with('#') {
+            Column::Ignored
+        } else if lowercase == "image" {
+            if columns.contains(&Column::Image) {
+                return Err(error("there is more than one image column".to_string()));
+            }
+            Column::Image
+        } else if lowercase == "hint" || lowercase.starts_with("hint:") {
+            //"hint:" and "explain:" are all ASCII, so what follows starts at the same byte in both.
+            let label = name.get(5..).map(|l| l.trim()).unwrap_or("");
+            hint_labels.push(if label.is_empty() {
+                None
+            } else {
+                Some(label.to_string())
+            });
+            Column::Hint(hint_labels.len() - 1)
+        } else if lowercase.starts_with("explain:") {
+            let question = name.get("explain:".len()..).map(|q| q.trim()).unwrap_or("");
+            explanations.push((i, question.to_string()));
+            //Filled in below.
+            Column::Ignored
+        } else if name.is_empty() {
+            return Err(error(
+                "the column has no name, so it can't be told apart from a question".to_string(),
+            ));
+        } else {
+            //Questions are picked by name ignoring case, like with --ask, so "Capital" and "capital" would be ambiguous.
+            if questions.iter().any(|q| q.to_lowercase() == lowercase) {
+                return Err(error(format!(
+                    "there is more than one \"{}\" column, ignoring case",
+                    name
+                )));
+            }
+            questions.push(name.to_string());
+            Column::Question(questions.len() - 1)
+        };
+        columns.push(column);
+    }
+
+    if questions.is_empty() {
+        return Err(header_error(
+            file,
+            0,
+            "there are no question columns".to_string(),
+        ));
+    }
+
+    for (i, question) in explanations {
+        let lowercase = question.to_lowercase();
+        let category = match questions.iter().position(|q| q.to_lowercase() == lowercase) {
+            Some(category) => category,
+            None => {
+                return Err(header_error(
+                    file,
+                    i,
+                    format!("there is no \"{}\" question to explain", question),
+                ))
+            }
+        };
+        if columns.contains(&Column::Explanation(category)) {
+            return Err(header_error(
+                file,
+                i,
+                format!("there is more than one explanation for \"{}\"", question),
+            ));
+        }
+        columns[i] = Column::Explanation(category);
+    }
+
+    Ok(Header {
+        number_of_hints: hint_labels.len() as i32,
+        questions,
+        hint_labels,
+        columns,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(names: &str) -> Result<Header, QuizLoadError> {
+        let mut reader = csv::Reader::from_reader(names.as_bytes());
+        read_header(&mut reader, Path::new("quiz.csv"))
+    }
+
+    fn error_column(names: &str) -> usize {
+        match header(names) {
+            Err(QuizLoadError::BadHeader { location, .. }) => location.column,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("{:?} should not be a valid header", names),
+        }
+    }
+
+    #[test]
+    fn columns_get_their_role_from_their_name() {
+        let header =
+            header("Explain:capital,Capital,#notes,HINT,image,hint: Flag,Currency\n").unwrap();
+        assert_eq!(
+            header.columns,
+            vec![
+                Column::Explanation(0),
+                Column::Question(0),
+                Column::Ignored,
+                Column::Hint(0),
+                Column::Image,
+                Column::Hint(1),
+                Column::Question(1),
+            ]
+        );
+        assert_eq!(header.questions, vec!["Capital", "Currency"]);
+        assert_eq!(header.hint_labels, vec![None, Some("Flag".to_string())]);
+        assert_eq!(header.number_of_hints, 2);
+    }
+
+    #[test]
+    fn unclear_headers_are_errors() {
+        assert_eq!(error_column("image,Capital,Image\n"), 3);
+        assert_eq!(error_column("image,,Capital\n"), 2);
+        assert_eq!(error_column("Capital,hint,Capital\n"), 3);
+        assert_eq!(error_column("image,hint,#notes\n"), 1);
+        assert_eq!(error_column("Capital,explain:Currency\n"), 2);
+        assert_eq!(error_column("explain:Capital,Capital,explain:Capital\n"), 3);
+        assert_eq!(error_column("Capital,capital\n"), 2);
+        assert_eq!(error_column("explain:capital,Capital,EXPLAIN:CAPITAL\n"), 3);
+    }
+}
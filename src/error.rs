@@ -0,0 +1,127 @@
+use image::ImageError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+//Where in a quiz file a problem was found. Lines and columns start at 1, and are 0 when they aren't known.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: u64,
+    pub column: usize,
+    //JSON and TOML slides are numbered from 1 instead, since the parsers don't say which line they came from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slide: Option<u64>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = self.file.display();
+        match self.slide {
+            Some(slide) => write!(f, "{}, slide {}", file, slide),
+            None if self.line == 0 => write!(f, "{}", file),
+            None if self.column == 0 => write!(f, "{}:{}", file, self.line),
+            None => write!(f, "{}:{}:{}", file, self.line, self.column),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum QuizLoadError {
+    //The quiz file itself could not be opened or read.
+    Io {
+        file: PathBuf,
+        error: io::Error,
+    },
+    Csv {
+        location: Location,
+        error: csv::Error,
+    },
+    MissingImage {
+        location: Location,
+        path: PathBuf,
+    },
+    BadImage {
+        location: Location,
+        path: PathBuf,
+        error: ImageError,
+    },
+    ColumnCount {
+        location: Location,
+        expected: usize,
+        found: usize,
+    },
+    EmptyDeck {
+        location: Location,
+    },
+    //Column names that don't say clearly what each column is for.
+    BadHeader {
+        location: Location,
+        message: String,
+    },
+    //A JSON or TOML deck that doesn't parse or doesn't fit the deck layout.
+    Parse {
+        location: Location,
+        message: String,
+    },
+}
+
+impl QuizLoadError {
+    pub fn location(&self) -> Option<&Location> {
+        match *self {
+            QuizLoadError::Io { .. } => None,
+            QuizLoadError::Csv { ref location, .. }
+            | QuizLoadError::MissingImage { ref location, .. }
+            | QuizLoadError::BadImage { ref location, .. }
+            | QuizLoadError::ColumnCount { ref location, .. }
+            | QuizLoadError::EmptyDeck { ref location }
+            | QuizLoadError::BadHeader { ref location, .. }
+            | QuizLoadError::Parse { ref location, .. } => Some(location),
+        }
+    }
+
+    //What went wrong, without the location.
+    pub fn message(&self) -> String {
+        match *self {
+            QuizLoadError::Io { ref error, .. } => error.to_string(),
+            QuizLoadError::Csv { ref error, .. } => error.to_string(),
+            QuizLoadError::MissingImage { ref path, .. } => {
+                format!("image {} does not exist", path.display())
+            }
+            QuizLoadError::BadImage {
+                ref path,
+                ref error,
+                ..
+            } => format!("image {} could not be decoded: {}", path.display(), error),
+            QuizLoadError::ColumnCount {
+                expected, found, ..
+            } => format!("expected {} columns but found {}", expected, found),
+            QuizLoadError::EmptyDeck { .. } => "the quiz has no slides".to_string(),
+            QuizLoadError::BadHeader { ref message, .. }
+            | QuizLoadError::Parse { ref message, .. } => message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for QuizLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QuizLoadError::Io { ref file, .. } => {
+                write!(f, "{}: {}", file.display(), self.message())
+            }
+            _ => write!(f, "{}: {}", self.location().unwrap(), self.message()),
+        }
+    }
+}
+
+impl Error for QuizLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            QuizLoadError::Io { ref error, .. } => Some(error),
+            QuizLoadError::Csv { ref error, .. } => Some(error),
+            QuizLoadError::BadImage { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use deck::{read_image, resolve_image_path, Header, Metadata, Quiz, Slide};
+use error::{Location, QuizLoadError};
+use question::{DEFAULT_CHOICES, MAX_CHOICES, MIN_CHOICES};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DeckFormat {
+    Csv,
+    Json,
+    Toml,
+}
+
+impl DeckFormat {
+    //Anything that isn't .json or .toml is read as CSV, like it was before there were other formats.
+    pub fn from_path(path: &Path) -> DeckFormat {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match extension.as_deref() {
+            Some("json") => DeckFormat::Json,
+            Some("toml") => DeckFormat::Toml,
+            _ => DeckFormat::Csv,
+        }
+    }
+}
+
+//A single answer, or a list of them where the first is canonical and the rest are aliases.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AnswerCell {
+    One(String),
+    Many(Vec<String>),
+}
+
+//Answers and explanations are keyed by question, so their order in the file doesn't matter.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SlideFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hints: Vec<String>,
+    #[serde(default)]
+    answers: BTreeMap<String, AnswerCell>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    explanations: BTreeMap<String, String>,
+}
+
+//The layout shared by JSON and TOML decks. TOML needs plain values before tables, hence the field order.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeckFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    choices: Option<usize>,
+    questions: Vec<String>,
+    //Shown in front of each slide's hints. An empty label shows the hint on its own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hint_labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    #[serde(default)]
+    slides: Vec<SlideFile>,
+}
+
+//Structured decks don't keep line numbers, so whole file problems are reported at line 0.
+fn file_location(file: &Path) -> Location {
+    Location {
+        file: file.to_path_buf(),
+        line: 0,
+        column: 0,
+        slide: None,
+    }
+}
+
+//Where a slide, or one of its columns, is in a deck of any format. `line` is Slide::line.
+//JSON and TOML slides get their number instead of a line and have no columns.
+pub fn slide_location(file: &Path, line: u64, column: usize) -> Location {
+    match DeckFormat::from_path(file) {
+        DeckFormat::Csv => Location {
+            file: file.to_path_buf(),
+            line,
+            column,
+            slide: None,
+        },
+        _ => Location {
+            slide: Some(line),
+            ..file_location(file)
+        },
+    }
+}
+
+//The header line for CSV decks. JSON and TOML decks list their questions once, so it is the whole file.
+pub fn question_location(file: &Path, column: usize) -> Location {
+    match DeckFormat::from_path(file) {
+        DeckFormat::Csv => Location {
+            file: file.to_path_buf(),
+            line: 1,
+            column,
+            slide: None,
+        },
+        _ => file_location(file),
+    }
+}
+
+//Both parsers end their messages with the position, which is already in the location.
+fn parse_error(file: &Path, message: String, line_col: Option<(usize, usize)>) -> QuizLoadError {
+    let message = match message.rfind(" at line ") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    };
+    let location = match line_col {
+        Some((line, column)) => Location {
+            file: file.to_path_buf(),
+            line: line as u64,
+            column,
+            slide: None,
+        },
+        None => file_location(file),
+    };
+    QuizLoadError::Parse { location, message }
+}
+
+pub fn read_deck_file(file: &Path, format: DeckFormat) -> Result<DeckFile, QuizLoadError> {
+    let text = fs::read_to_string(file).map_err(|error| QuizLoadError::Io {
+        file: file.to_path_buf(),
+        error,
+    })?;
+    match format {
+        DeckFormat::Json => serde_json::from_str(&text)
+            .map_err(|e| parse_error(file, e.to_string(), Some((e.line(), e.column())))),
+        //toml counts lines and columns from 0.
+        DeckFormat::Toml => toml::from_str(&text).map_err(|e| {
+            let line_col = e.line_col().map(|(line, column)| (line + 1, column + 1));
+            parse_error(file, e.to_string(), line_col)
+        }),
+        DeckFormat::Csv => Err(parse_error(
+            file,
+            "CSV quizzes are read with read_header and read_slides".to_string(),
+            None,
+        )),
+    }
+}
+
+//The deck's own number of choices, if it sets one.
+pub fn deck_choices(deck: &DeckFile, file: &Path) -> Result<Option<usize>, QuizLoadError> {
+    match deck.choices {
+        Some(n) if !(MIN_CHOICES..=MAX_CHOICES).contains(&n) => Err(parse_error(
+            file,
+            format!(
+                "choices must be a number from {} to {}, not {}",
+                MIN_CHOICES, MAX_CHOICES, n
+            ),
+            None,
+        )),
+        choices => Ok(choices),
+    }
+}
+
+//Like read_slides, a slide that fails to load is left out.
+//Slides are numbered from 1 in place of line numbers, and their locations say so.
+pub fn read_structured_slides(
+    deck: &DeckFile,
+    file: &Path,
+    collect_errors: bool,
+) -> (Header, Vec<Slide>, Vec<QuizLoadError>) {
+    let number_of_hints = deck.slides.iter().map(|s| s.hints.len()).max().unwrap_or(0);
+    let mut hint_labels: Vec<Option<String>> = deck
+        .hint_labels
+        .iter()
+        .map(|l| if l.is_empty() { None } else { Some(l.clone()) })
+        .collect();
+    hint_labels.resize(number_of_hints.max(hint_labels.len()), None);
+    let header = Header::new(hint_labels, deck.questions.clone());
+
+    let mut slides: Vec<Slide> = Vec::new();
+    let mut errors: Vec<QuizLoadError> = Vec::new();
+    for (i, slide_file) in deck.slides.iter().enumerate() {
+        match read_structured_slide(slide_file, i as u64 + 1, &header, file) {
+            Ok(slide) => slides.push(slide),
+            Err(error) => {
+                errors.push(error);
+                if !collect_errors {
+                    break;
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() && slides.is_empty() {
+        errors.push(QuizLoadError::EmptyDeck {
+            location: file_location(file),
+        });
+    }
+
+    (header, slides, errors)
+}
+
+fn read_structured_slide(
+    slide_file: &SlideFile,
+    number: u64,
+    header: &Header,
+    file: &Path,
+) -> Result<Slide, QuizLoadError> {
+    let location = Location {
+        slide: Some(number),
+        ..file_location(file)
+    };
+
+    let keys = slide_file
+        .answers
+        .keys()
+        .chain(slide_file.explanations.keys());
+    for key in keys {
+        if !header.questions.contains(key) {
+            return Err(QuizLoadError::Parse {
+                location,
+                message: format!("unknown question \"{}\"", key),
+            });
+        }
+    }
+
+    let mut hints: Vec<String> = slide_file
+        .hints
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+    hints.resize(header.number_of_hints as usize, String::new());
+
+    let mut slide = Slide {
+        line: number,
+        image_path: None,
+        image: None,
+        hints,
+        answers: Vec::new(),
+        aliases: Vec::new(),
+        explanations: Vec::new(),
+    };
+
+    if let Some(ref value) = slide_file.image {
+        let value = value.trim();
+        if !value.is_empty() {
+            slide.image = Some(read_image(location, &resolve_image_path(file, value))?);
+            slide.image_path = Some(value.into());
+        }
+    }
+
+    for question in header.questions.iter() {
+        let (canonical, aliases) = match slide_file.answers.get(question) {
+            Some(AnswerCell::One(answer)) => (answer.trim().to_string(), Vec::new()),
+            Some(AnswerCell::Many(answers)) => {
+                let mut answers = answers.iter().map(|a| a.trim().to_string());
+                let canonical = answers.next().unwrap_or_default();
+                (canonical, answers.filter(|a| !a.is_empty()).collect())
+            }
+            None => (String::new(), Vec::new()),
+        };
+        slide.answers.push(canonical);
+        slide.aliases.push(aliases);
+        slide.explanations.push(
+            slide_file
+                .explanations
+                .get(question)
+                .cloned()
+                .unwrap_or_default(),
+        );
+    }
+
+    Ok(slide)
+}
+
+fn to_deck_file(quiz: &Quiz) -> DeckFile {
+    let slides = quiz
+        .slides
+        .iter()
+        .map(|slide| {
+            let mut answers = BTreeMap::new();
+            let mut explanations = BTreeMap::new();
+            for (category, question) in quiz.header.questions.iter().enumerate() {
+                let accepted = slide.accepted_answers(category);
+                let cell = match accepted.len() {
+                    0 => continue,
+                    1 => AnswerCell::One(accepted[0].clone()),
+                    _ => AnswerCell::Many(accepted),
+                };
+                answers.insert(question.clone(), cell);
+                match slide.explanations.get(category) {
+                    Some(explanation) if !explanation.is_empty() => {
+                        explanations.insert(question.clone(), explanation.clone());
+                    }
+                    _ => (),
+                }
+            }
+
+            //Hints line up with their labels, so only the empty ones at the end can go.
+            let mut hints = slide.hints.clone();
+            while hints.last().map(|h| h.is_empty()) == Some(true) {
+                hints.pop();
+            }
+
+            SlideFile {
+                image: slide
+                    .image_path
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().into_owned()),
+                hints,
+                answers,
+                explanations,
+            }
+        })
+        .collect();
+
+    //Leaving out the default lets --choices or a later default change still apply.
+    let choices = if quiz.choices == DEFAULT_CHOICES {
+        None
+    } else {
+        Some(quiz.choices)
+    };
+
+    let hint_labels = if quiz.header.hint_labels.iter().all(|l| l.is_none()) {
+        Vec::new()
+    } else {
+        quiz.header
+            .hint_labels
+            .iter()
+            .map(|l| l.clone().unwrap_or_default())
+            .collect()
+    };
+
+    DeckFile {
+        choices,
+        questions: quiz.header.questions.clone(),
+        hint_labels,
+        metadata: quiz.metadata.clone(),
+        slides,
+    }
+}
+
+//Writes the quiz as JSON or TOML, going by the extension.
+//Image paths are copied as written, so the new file should go in the same directory as the old one.
+pub fn write_quiz(quiz: &Quiz, path: &Path) -> io::Result<()> {
+    let deck = to_deck_file(quiz);
+    let text = match DeckFormat::from_path(path) {
+        DeckFormat::Json => serde_json::to_string_pretty(&deck).map_err(io::Error::from)?,
+        DeckFormat::Toml => toml::to_string(&deck)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?,
+        DeckFormat::Csv => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "quizzes can only be written as .json or .toml",
+            ))
+        }
+    };
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use std::env;
+    use std::path::PathBuf;
+
+    //Named after the process so test runs at the same time don't share files.
+    fn deck_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rusty_quiz_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn assert_same_deck(a: &Quiz, b: &Quiz) {
+        assert_eq!(a.header.questions, b.header.questions);
+        assert_eq!(a.header.hint_labels, b.header.hint_labels);
+        assert_eq!(a.choices, b.choices);
+        assert_eq!(a.slides.len(), b.slides.len());
+        for (a, b) in a.slides.iter().zip(b.slides.iter()) {
+            assert_eq!(a.image_path, b.image_path);
+            assert_eq!(
+                a.image.as_ref().map(|i| i.dimensions()),
+                b.image.as_ref().map(|i| i.dimensions())
+            );
+            assert_eq!(a.hints, b.hints);
+            assert_eq!(a.answers, b.answers);
+            assert_eq!(a.aliases, b.aliases);
+            assert_eq!(a.explanations, b.explanations);
+        }
+    }
+
+    #[test]
+    fn csv_decks_survive_conversion() {
+        let dir = deck_dir("convert");
+        RgbaImage::new(3, 2).save(dir.join("flag.png")).unwrap();
+        fs::write(
+            dir.join("deck.csv"),
+            "image,hint:Continent,hint,Country,Currency,explain:Currency\n\
+             flag.png,Europe,,France,Euro,Since 2002\n\
+             ,Americas,North,United States|USA,Dollar|USD,\n",
+        )
+        .unwrap();
+        let csv = Quiz::from_path(dir.join("deck.csv")).unwrap();
+
+        for name in ["deck.json", "deck.toml"].iter() {
+            write_quiz(&csv, &dir.join(name)).unwrap();
+            let converted = Quiz::from_path(dir.join(name)).unwrap();
+            assert_same_deck(&csv, &converted);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn slide_problems_point_at_the_slide() {
+        let dir = deck_dir("locations");
+        let file = dir.join("deck.toml");
+        fs::write(
+            &file,
+            "questions = [\"Capital\"]\n\n\
+             [[slides]]\nanswers = { Capital = \"Paris\" }\n\n\
+             [[slides]]\nanswers = { Currency = \"Euro\" }\n",
+        )
+        .unwrap();
+        let error = Quiz::from_path(&file).err().unwrap();
+        let location = error.location().unwrap().clone();
+        assert_eq!(
+            (location.line, location.column, location.slide),
+            (0, 0, Some(2))
+        );
+        assert_eq!(
+            error.to_string(),
+            format!("{}, slide 2: unknown question \"Currency\"", file.display())
+        );
+
+        fs::write(&file, "questions = [\"Capital\"]\nchoices = = 4\n").unwrap();
+        let location = Quiz::from_path(&file)
+            .err()
+            .unwrap()
+            .location()
+            .unwrap()
+            .clone();
+        assert_eq!((location.line, location.slide), (2, None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
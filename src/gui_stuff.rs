@@ -1,59 +1,48 @@
-    let img_right = image::open("./resources/icons8-checked-50.png").unwrap();
-    let img_wrong = image::open("./resources/icons8-cancel-50.png").unwrap();
-    let img_continue = image::open("./resources/icons8-circled-right-50.png").unwrap();
+use gl::types::*;
+use image::{Rgba, RgbaImage};
+use rusty_quiz;
 
+use text::{render_markup, render_text, Alignment, Fonts};
+use {add_colored_box, add_textured_box, TEXT_COLOR};
 
+const BUTTON_FILL_COLOR: (f32, f32, f32) = (0.6, 0.6, 0.6);
+const BUTTON_BORDER_COLOR: (f32, f32, f32) = (0.2, 0.2, 0.2);
 
+const BUTTON_FILL_COLOR_HOVER: (f32, f32, f32) = (0.75, 0.75, 0.75);
+const BUTTON_BORDER_COLOR_HOVER: (f32, f32, f32) = (0.3, 0.3, 0.3);
 
+const BUTTON_FILL_COLOR_PRESSED: (f32, f32, f32) = (0.5, 0.5, 0.5);
+const BUTTON_BORDER_COLOR_PRESSED: (f32, f32, f32) = (0.0, 0.0, 0.0);
 
+//Shown once a question is answered.
+const BUTTON_FILL_COLOR_RIGHT: (f32, f32, f32) = (0.3, 0.65, 0.3);
+const BUTTON_FILL_COLOR_WRONG: (f32, f32, f32) = (0.75, 0.3, 0.3);
 
+const BUTTON_TEXT_COLOR: (f32, f32, f32) = (1.0, 1.0, 1.0);
 
+const BUTTON_BORDER_THICKNESS: f32 = 2.0;
+const BUTTON_PADDING: f32 = 6.0;
+const BUTTON_SPACING: f32 = 10.0;
 
+const QUESTION_TEXT_SIZE: f32 = 24.0;
+const ANSWER_TEXT_SIZE: f32 = 20.0;
+const EXPLANATION_TEXT_SIZE: f32 = 18.0;
+const ANSWER_COLUMNS: usize = 2;
+//Text wider than these is wrapped onto more lines.
+const TEXT_MAX_WIDTH: f32 = 800.0;
+const ANSWER_MAX_WIDTH: f32 = 400.0;
 
+const ENTRY_WIDTH: f32 = 400.0;
+const ENTRY_FILL_COLOR: (f32, f32, f32) = (1.0, 1.0, 1.0);
+const ENTRY_CURSOR: char = '_';
 
+//Lower layers are drawn in front of higher ones.
+const BORDER_LAYER: f32 = 0.0;
+const FILL_LAYER: f32 = -0.1;
+const TEXT_LAYER: f32 = -0.2;
 
-
-struct CurrentSlideInfo<'a> {
-    slide: &'a Slide,
-    render_info: CurrentSlideRenderInfo,
-    questions: Vec<Question>,
-}
-
-
-
-
-
-
-
-
-#[derive(PartialEq)]
-enum QuestionStatus {
-    right,
-    wrong,
-    untouched,
-}
-struct Question {
-    question: RgbaImage,
-    correct_answer: TexturedButton,
-    wrong_answers: Vec<TexturedButton>,
-    status: QuestionStatus,
-}
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-trait Renderable {
-    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32));
+pub trait Renderable {
+    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32), texture_size: (f32, f32));
     fn width(&self) -> f32;
     fn height(&self) -> f32;
 
@@ -62,7 +51,8 @@ trait Renderable {
     }
 }
 
-trait TextureUser {
+//Anything that keeps its pixels in the shared texture.
+pub trait TextureUser {
     fn required_width(&self) -> f32;
     fn required_height(&self) -> f32;
 
@@ -71,237 +61,107 @@ trait TextureUser {
     }
 
     fn set_offset(&mut self, offset: (f32, f32));
-    fn get_offset(&self) -> (f32, f32);
 
     fn update_texture(&self, texture: &mut RgbaImage);
 }
 
-
-
-
-
-
-
-trait Clickable {
+pub trait Clickable {
     fn update_state(&mut self, mouse_pos: (f32, f32), button_pressed: bool);
     fn is_clicked(&self) -> bool;
 }
 
-
-
-struct CurrentSlideRenderInfo {
-    image_offset: (f32, f32),
-    right_offset: (f32, f32),
-    wrong_offset: (f32, f32),
-    continue_offset: (f32, f32),
-}
-
-
-
-
-
-
-
-
-impl<'a> CurrentSlideInfo<'a> {
-    fn new(
-        slide: &Slide,
-        all_slides: &Vec<Slide>,
-        slide_offset: (f32, f32),
-        right_offset: (f32, f32),
-        wrong_offset: (f32, f32),
-        continue_offset: (f32, f32),
-    ) -> CurrentSlideInfo<'a> {
-        let render_info = CurrentSlideRenderInfo {
-            image_offset: slide_offset,
-            right_offset: right_offset,
-            wrong_offset: wrong_offset,
-            continue_offset: continue_offset,
-        };
-        let mut wrong_answers: Vec<Vec<String>> = Vec::new();
-        let mut rng = rand::thread_rng();
-        for category in 0..slide.answers.len() {
-            let mut random_answers = Vec::new();
-            while random_answers.len() < 3 {
-                let random_answer = rng
-                    .choose(all_slides)
-                    .expect("all_slides empty! Please fix!")
-                    .answers[category];
-                if random_answer != slide.answers[category] {
-                    random_answers.push(random_answer);
-                }
-            }
-            wrong_answers.push(random_answers);
-        }
-        CurrentSlideInfo {
-            slide: slide,
-            render_info: render_info,
-            questions: questions,
-        }
-    }
-
-    fn is_solved(&self) -> bool {
-        let mut solved = true;
-        for q in self.questions.iter() {
-            if q.status != QuestionStatus::right {
-                solved = false;
-            }
-        }
-        solved
+fn copy_into_texture(image: &RgbaImage, offset: (f32, f32), texture: &mut RgbaImage) {
+    for (x, y, pixel) in image.enumerate_pixels() {
+        texture.put_pixel(offset.0 as u32 + x, offset.1 as u32 + y, *pixel);
     }
 }
 
-fn add_colored_box(
-    window_position: (f32, f32),
-    layer: f32,
-    color: (f32, f32, f32),
-    box_size: (f32, f32),
-    window_size: (f32, f32),
-    verts: &mut Vec<GLfloat>,
-) {
-    let pos = (
-        (window_position.0 * 2.0) / window_size.0 - 1.0,
-        (window_position.1 * -2.0) / window_size.1 + 1.0,
-    );
-    let box_size = (
-        (box_size.0 * 2.0) / window_size.0,
-        (box_size.1 * -2.0) / window_size.1,
-    );
-
-    //triangle 1
-    //pos
-    verts.push(pos.0);
-    verts.push(pos.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-
-    //pos
-    verts.push(pos.0 + box_size.0);
-    verts.push(pos.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-
-    //pos
-    verts.push(pos.0 + box_size.0);
-    verts.push(pos.1 + box_size.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-
-    //triangle 2
-    //pos
-    verts.push(pos.0);
-    verts.push(pos.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-
-    //pos
-    verts.push(pos.0);
-    verts.push(pos.1 + box_size.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-
-    //pos
-    verts.push(pos.0 + box_size.0);
-    verts.push(pos.1 + box_size.1);
-    verts.push(layer);
-    //color
-    verts.push(color.0);
-    verts.push(color.1);
-    verts.push(color.2);
-    //tex coord
-    verts.push(0.0);
-    verts.push(0.0);
-    //enable texture
-    verts.push(0.0);
-}
-
-
-
-
-
-
-
-
-
-
-
 //Textured button stuff
-struct TexturedButton {
+pub struct TexturedButton {
     position: (f32, f32),
     texture: RgbaImage,
     texture_offset: (f32, f32),
+    fill_color: Option<(f32, f32, f32)>,
     mouse_hover: bool,
     mouse_down: bool,
     clicked: bool,
 }
 
-impl TexturedButton{
-    fn new(position: (f32,f32),texture: RgbaImage,texture_offset:(f32,f32)) -> TexturedButton {
+impl TexturedButton {
+    pub fn new(position: (f32, f32), texture: RgbaImage) -> TexturedButton {
         TexturedButton {
-            position: position,
-            texture: texture,
-            texture_offset: texture_offset,
+            position,
+            texture,
+            texture_offset: (0.0, 0.0),
+            fill_color: None,
+            mouse_hover: false,
             mouse_down: false,
             clicked: false,
         }
     }
+
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    //Overrides the fill used for the normal, hover and pressed states.
+    pub fn set_fill_color(&mut self, color: Option<(f32, f32, f32)>) {
+        self.fill_color = color;
+    }
 }
 
 impl Renderable for TexturedButton {
-    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32)) {
-        if self.mouse_hover {
-            if self.mouse_down {
-            add_colored_box((self.position.0 + BUTTON_BORDER_THICKNESS,self.position.1 + BUTTON_BORDER_THICKNESS),0.0,BUTTON_BORDER_COLOR_HOVER,(texture.width()-BUTTON_BORDER_THICKNESS,texture.height()-BUTTON_BORDER_THICKNESS),window_size,verts);
+    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32), texture_size: (f32, f32)) {
+        let (fill_color, border_color) = if self.mouse_hover && self.mouse_down {
+            (BUTTON_FILL_COLOR_PRESSED, BUTTON_BORDER_COLOR_PRESSED)
+        } else if self.mouse_hover {
+            (BUTTON_FILL_COLOR_HOVER, BUTTON_BORDER_COLOR_HOVER)
         } else {
-
-        }
-        }
+            (BUTTON_FILL_COLOR, BUTTON_BORDER_COLOR)
+        };
+        let fill_color = self.fill_color.unwrap_or(fill_color);
+
+        add_colored_box(
+            self.position,
+            BORDER_LAYER,
+            border_color,
+            self.size(),
+            window_size,
+            verts,
+        );
+        add_colored_box(
+            (
+                self.position.0 + BUTTON_BORDER_THICKNESS,
+                self.position.1 + BUTTON_BORDER_THICKNESS,
+            ),
+            FILL_LAYER,
+            fill_color,
+            (
+                self.width() - BUTTON_BORDER_THICKNESS * 2.0,
+                self.height() - BUTTON_BORDER_THICKNESS * 2.0,
+            ),
+            window_size,
+            verts,
+        );
+        add_textured_box(
+            (
+                self.position.0 + BUTTON_PADDING,
+                self.position.1 + BUTTON_PADDING,
+            ),
+            TEXT_LAYER,
+            self.texture_offset,
+            BUTTON_TEXT_COLOR,
+            self.required_size(),
+            window_size,
+            texture_size,
+            verts,
+        );
     }
     fn width(&self) -> f32 {
-        self.texture.width()
+        self.texture.width() as f32 + BUTTON_PADDING * 2.0
     }
-    fn height(&self) -> f32{
-        self.texture.height()
+    fn height(&self) -> f32 {
+        self.texture.height() as f32 + BUTTON_PADDING * 2.0
     }
 }
 
@@ -318,33 +178,24 @@ impl TextureUser for TexturedButton {
         self.texture_offset = offset;
     }
 
-    fn get_offset(&self) -> (f32, f32) {
-        self.texture_offset
-    }
-
     fn update_texture(&self, texture: &mut RgbaImage) {
-        for (x, y, pixel) in self.texture.enumerate_pixels() {
-            texture.put_pixel(
-                self.texture_offset.0 as u32 + x,
-                self.texture_offset.1 as u32 + y,
-                *pixel,
-            );
-        }
+        copy_into_texture(&self.texture, self.texture_offset, texture);
     }
 }
 
 impl Clickable for TexturedButton {
     fn update_state(&mut self, mouse_pos: (f32, f32), button_pressed: bool) {
-        let lower_left_corner = (
-            self.position.0 + self.texture.width() as f32,
-            self.position.1 + self.texture.height() as f32,
+        let lower_right_corner = (
+            self.position.0 + self.width(),
+            self.position.1 + self.height(),
         );
         self.mouse_hover = mouse_pos.0 > self.position.0
             && mouse_pos.1 > self.position.1
-            && mouse_pos.0 < lower_left_corner.0
-            && mouse_pos.1 < lower_left_corner.1;
+            && mouse_pos.0 < lower_right_corner.0
+            && mouse_pos.1 < lower_right_corner.1;
         if self.mouse_hover {
             if button_pressed {
+                self.clicked = false;
                 self.mouse_down = true;
             } else {
                 self.clicked = self.mouse_down;
@@ -361,123 +212,700 @@ impl Clickable for TexturedButton {
     }
 }
 
+//Text entry stuff
+//A one line box the answer is typed into.
+//The text is rendered again on every change, so it keeps a fixed size spot in the texture and only shows the end of long answers.
+pub struct TextEntry {
+    position: (f32, f32),
+    text: String,
+    image: RgbaImage,
+    texture_offset: (f32, f32),
+    fill_color: (f32, f32, f32),
+    text_color: (f32, f32, f32),
+    finished: bool,
+}
+
+impl TextEntry {
+    pub fn new(fonts: &Fonts) -> TextEntry {
+        let mut entry = TextEntry {
+            position: (0.0, 0.0),
+            text: String::new(),
+            image: RgbaImage::new(0, 0),
+            texture_offset: (0.0, 0.0),
+            fill_color: ENTRY_FILL_COLOR,
+            text_color: TEXT_COLOR,
+            finished: false,
+        };
+        entry.render_text(fonts);
+        entry
+    }
+
+    //The cursor means there is always something to render, even before anything is typed.
+    fn render_text(&mut self, fonts: &Fonts) {
+        let text = if self.finished {
+            self.text.clone()
+        } else {
+            format!("{}{}", self.text, ENTRY_CURSOR)
+        };
+        self.image = render_text(fonts, ANSWER_TEXT_SIZE, &text);
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    //Returns true if the text changed. Leading whitespace and control characters are ignored.
+    pub fn push(&mut self, c: char, fonts: &Fonts) -> bool {
+        if self.finished || c.is_control() || (c.is_whitespace() && self.text.is_empty()) {
+            return false;
+        }
+        self.text.push(c);
+        self.render_text(fonts);
+        true
+    }
+
+    pub fn pop(&mut self, fonts: &Fonts) -> bool {
+        if self.finished || self.text.pop().is_none() {
+            return false;
+        }
+        self.render_text(fonts);
+        true
+    }
+
+    //Stops any more typing and colors the box to show how the answer went.
+    pub fn finish(&mut self, fill_color: (f32, f32, f32), fonts: &Fonts) {
+        self.finished = true;
+        self.fill_color = fill_color;
+        self.text_color = BUTTON_TEXT_COLOR;
+        self.render_text(fonts);
+    }
+
+    fn visible_width(&self) -> f32 {
+        (self.image.width() as f32).min(ENTRY_WIDTH)
+    }
+}
+
+impl Renderable for TextEntry {
+    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32), texture_size: (f32, f32)) {
+        add_colored_box(
+            self.position,
+            BORDER_LAYER,
+            BUTTON_BORDER_COLOR,
+            self.size(),
+            window_size,
+            verts,
+        );
+        add_colored_box(
+            (
+                self.position.0 + BUTTON_BORDER_THICKNESS,
+                self.position.1 + BUTTON_BORDER_THICKNESS,
+            ),
+            FILL_LAYER,
+            self.fill_color,
+            (
+                self.width() - BUTTON_BORDER_THICKNESS * 2.0,
+                self.height() - BUTTON_BORDER_THICKNESS * 2.0,
+            ),
+            window_size,
+            verts,
+        );
+        add_textured_box(
+            (
+                self.position.0 + BUTTON_PADDING,
+                self.position.1 + BUTTON_PADDING,
+            ),
+            TEXT_LAYER,
+            self.texture_offset,
+            self.text_color,
+            (self.visible_width(), self.required_height()),
+            window_size,
+            texture_size,
+            verts,
+        );
+    }
+    fn width(&self) -> f32 {
+        ENTRY_WIDTH + BUTTON_PADDING * 2.0
+    }
+    fn height(&self) -> f32 {
+        self.image.height() as f32 + BUTTON_PADDING * 2.0
+    }
+}
+
+impl TextureUser for TextEntry {
+    fn required_width(&self) -> f32 {
+        ENTRY_WIDTH
+    }
+
+    fn required_height(&self) -> f32 {
+        self.image.height() as f32
+    }
+
+    fn set_offset(&mut self, offset: (f32, f32)) {
+        self.texture_offset = offset;
+    }
+
+    //Clears the whole spot first since the new text can be shorter than the old.
+    fn update_texture(&self, texture: &mut RgbaImage) {
+        let offset = (self.texture_offset.0 as u32, self.texture_offset.1 as u32);
+        for y in 0..self.required_height() as u32 {
+            for x in 0..ENTRY_WIDTH as u32 {
+                texture.put_pixel(offset.0 + x, offset.1 + y, Rgba { data: [0, 0, 0, 0] });
+            }
+        }
+        let skip = self.image.width() - self.visible_width() as u32;
+        for (x, y, pixel) in self.image.enumerate_pixels() {
+            if x >= skip {
+                texture.put_pixel(offset.0 + x - skip, offset.1 + y, *pixel);
+            }
+        }
+    }
+}
+
+//Icon stuff
+pub struct Icons {
+    pub right: RgbaImage,
+    pub wrong: RgbaImage,
+    pub next: RgbaImage,
+}
 
+struct Icon {
+    image: RgbaImage,
+    texture_offset: (f32, f32),
+}
 
+impl Icon {
+    fn new(image: &RgbaImage) -> Icon {
+        Icon {
+            image: image.clone(),
+            texture_offset: (0.0, 0.0),
+        }
+    }
 
+    fn render_at(
+        &self,
+        position: (f32, f32),
+        verts: &mut Vec<GLfloat>,
+        window_size: (f32, f32),
+        texture_size: (f32, f32),
+    ) {
+        add_textured_box(
+            position,
+            TEXT_LAYER,
+            self.texture_offset,
+            (1.0, 1.0, 1.0),
+            self.required_size(),
+            window_size,
+            texture_size,
+            verts,
+        );
+    }
+}
 
+impl TextureUser for Icon {
+    fn required_width(&self) -> f32 {
+        self.image.width() as f32
+    }
 
+    fn required_height(&self) -> f32 {
+        self.image.height() as f32
+    }
 
+    fn set_offset(&mut self, offset: (f32, f32)) {
+        self.texture_offset = offset;
+    }
 
+    fn update_texture(&self, texture: &mut RgbaImage) {
+        copy_into_texture(&self.image, self.texture_offset, texture);
+    }
+}
 
 //Question stuff
-struct Question {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QuestionStatus {
+    Right,
+    Wrong,
+    Untouched,
+}
+
+//The prompt with one button per choice, laid out in a grid underneath it.
+//Once a choice is made it is marked right or wrong, the right answer is highlighted and a continue button shows up.
+//Typed questions have a text entry instead of the buttons and show the right answer under it after a wrong guess.
+//Any explanation goes under the answers once the question is answered.
+pub struct Question {
+    position: (f32, f32),
     question: RgbaImage,
-    correct_answer: TexturedButton,
-    wrong_answers: Vec<TexturedButton>,
+    question_offset: (f32, f32),
+    answers: Vec<TexturedButton>,
+    entry: Option<TextEntry>,
+    reveal: Option<RgbaImage>,
+    reveal_offset: (f32, f32),
+    explanation: Option<RgbaImage>,
+    explanation_offset: (f32, f32),
+    right_answer: usize,
+    chosen: Option<usize>,
     status: QuestionStatus,
+    right_icon: Icon,
+    wrong_icon: Icon,
+    continue_button: TexturedButton,
 }
 
-impl Renderable for Question {
-    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32)) {
-        add_textured_box()
+impl Question {
+    pub fn new(question: &rusty_quiz::Question, icons: &Icons, fonts: &Fonts) -> Question {
+        let answers = question
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                let text = format!("[{}] {}", i + 1, choice);
+                let image = render_markup(
+                    fonts,
+                    ANSWER_TEXT_SIZE,
+                    &text,
+                    ANSWER_MAX_WIDTH,
+                    Alignment::Center,
+                );
+                TexturedButton::new((0.0, 0.0), image)
+            })
+            .collect();
+
+        Question::with_answers(question, answers, None, None, icons, fonts)
     }
-    fn width(&self) -> f32 {
-        let mut width = 0;
-        width = width.max(question.width());
-        width = width.max(correct_answer.width());
-        for b in wrong_answers.iter() {
-            width = width.max(b.width());
+
+    //Each choice is a picture, numbered above it.
+    pub fn with_images(
+        question: &rusty_quiz::Question,
+        images: Vec<RgbaImage>,
+        icons: &Icons,
+        fonts: &Fonts,
+    ) -> Question {
+        let answers = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                let number = render_text(fonts, ANSWER_TEXT_SIZE, &format!("[{}]", i + 1));
+                let mut texture = RgbaImage::new(
+                    number.width().max(image.width()),
+                    number.height() + image.height(),
+                );
+                let number_left = (texture.width() - number.width()) / 2;
+                copy_into_texture(&number, (number_left as f32, 0.0), &mut texture);
+                copy_into_texture(image, (0.0, number.height() as f32), &mut texture);
+                TexturedButton::new((0.0, 0.0), texture)
+            })
+            .collect();
+
+        Question::with_answers(question, answers, None, None, icons, fonts)
+    }
+
+    pub fn typed(question: &rusty_quiz::Question, icons: &Icons, fonts: &Fonts) -> Question {
+        let reveal = format!("Answer: {}", question.choices[question.right_answer]);
+        Question::with_answers(
+            question,
+            Vec::new(),
+            Some(TextEntry::new(fonts)),
+            Some(render_markup(
+                fonts,
+                ANSWER_TEXT_SIZE,
+                &reveal,
+                TEXT_MAX_WIDTH,
+                Alignment::Left,
+            )),
+            icons,
+            fonts,
+        )
+    }
+
+    fn with_answers(
+        question: &rusty_quiz::Question,
+        answers: Vec<TexturedButton>,
+        entry: Option<TextEntry>,
+        reveal: Option<RgbaImage>,
+        icons: &Icons,
+        fonts: &Fonts,
+    ) -> Question {
+        Question {
+            position: (0.0, 0.0),
+            question: render_markup(
+                fonts,
+                QUESTION_TEXT_SIZE,
+                &question.prompt,
+                TEXT_MAX_WIDTH,
+                Alignment::Left,
+            ),
+            question_offset: (0.0, 0.0),
+            answers,
+            entry,
+            reveal,
+            reveal_offset: (0.0, 0.0),
+            explanation: question.explanation.as_ref().map(|e| {
+                render_markup(
+                    fonts,
+                    EXPLANATION_TEXT_SIZE,
+                    e,
+                    TEXT_MAX_WIDTH,
+                    Alignment::Left,
+                )
+            }),
+            explanation_offset: (0.0, 0.0),
+            right_answer: question.right_answer,
+            chosen: None,
+            status: QuestionStatus::Untouched,
+            right_icon: Icon::new(&icons.right),
+            wrong_icon: Icon::new(&icons.wrong),
+            continue_button: TexturedButton::new((0.0, 0.0), icons.next.clone()),
         }
-        width * ((self.wrong_answers.size()) as f32 / 2.0) + 10.0
     }
-    fn height(&self) -> f32 {
-        let mut height = 0;
-        height = width.max(question.height());
-        height = width.max(correct_answer.height());
-        for b in wrong_answers.iter() {
-            width = width.max(b.height());
+
+    //Each cell has room for a button and the icon marking it.
+    fn cell_size(&self) -> (f32, f32) {
+        let mut size: (f32, f32) = (0.0, 0.0);
+        for b in self.answers.iter() {
+            size.0 = size.0.max(b.width());
+            size.1 = size.1.max(b.height());
         }
-        height * 2.0 + 20.0
+        size.0 += BUTTON_SPACING + self.right_icon.required_width();
+        size.1 = size.1.max(self.right_icon.required_height());
+        (size.0 + BUTTON_SPACING, size.1 + BUTTON_SPACING)
     }
 
-    fn size(&self) -> (f32, f32) {
-        (self.width(), self.height())
+    fn rows(&self) -> usize {
+        self.answers.len().div_ceil(ANSWER_COLUMNS)
+    }
+
+    //The entry with room for an icon beside it.
+    fn entry_row_height(&self, entry: &TextEntry) -> f32 {
+        entry.height().max(self.right_icon.required_height()) + BUTTON_SPACING
+    }
+
+    //Everything between the prompt and the continue button.
+    fn answers_size(&self) -> (f32, f32) {
+        match (&self.entry, &self.reveal) {
+            (Some(entry), Some(reveal)) => (
+                (entry.width() + BUTTON_SPACING + self.right_icon.required_width())
+                    .max(reveal.width() as f32),
+                self.entry_row_height(entry) + reveal.height() as f32 + BUTTON_SPACING,
+            ),
+            _ => {
+                let cell_size = self.cell_size();
+                (
+                    cell_size.0 * ANSWER_COLUMNS as f32,
+                    cell_size.1 * self.rows() as f32,
+                )
+            }
+        }
+    }
+
+    fn explanation_height(&self) -> f32 {
+        match self.explanation {
+            Some(ref explanation) => explanation.height() as f32 + BUTTON_SPACING,
+            None => 0.0,
+        }
+    }
+
+    fn explanation_position(&self) -> (f32, f32) {
+        let top = self.position.1 + self.question.height() as f32 + BUTTON_SPACING;
+        (self.position.0, top + self.answers_size().1)
+    }
+
+    fn reveal_position(&self) -> (f32, f32) {
+        let top = self.position.1 + self.question.height() as f32 + BUTTON_SPACING;
+        match self.entry {
+            Some(ref entry) => (self.position.0, top + self.entry_row_height(entry)),
+            None => (self.position.0, top),
+        }
+    }
+
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+
+        let cell_size = self.cell_size();
+        let top = position.1 + self.question.height() as f32 + BUTTON_SPACING;
+        for (i, b) in self.answers.iter_mut().enumerate() {
+            b.set_position((
+                position.0 + (i % ANSWER_COLUMNS) as f32 * cell_size.0,
+                top + (i / ANSWER_COLUMNS) as f32 * cell_size.1,
+            ));
+        }
+        if let Some(ref mut entry) = self.entry {
+            entry.set_position((position.0, top));
+        }
+        let answers_height = self.answers_size().1 + self.explanation_height();
+        self.continue_button
+            .set_position((position.0, top + answers_height));
+    }
+
+    //The answer picked with the mouse this frame, if any.
+    pub fn clicked_answer(&self) -> Option<usize> {
+        self.answers.iter().position(|b| b.is_clicked())
+    }
+
+    //Only the first choice counts. Anything after it is ignored.
+    pub fn choose(&mut self, choice: usize) -> QuestionStatus {
+        if self.status == QuestionStatus::Untouched {
+            self.chosen = Some(choice);
+            self.status = if choice == self.right_answer {
+                QuestionStatus::Right
+            } else {
+                QuestionStatus::Wrong
+            };
+            //The answers stop reacting to the mouse, so clear any hover left over.
+            for b in self.answers.iter_mut() {
+                b.update_state((-1.0, -1.0), false);
+            }
+            self.answers[self.right_answer].set_fill_color(Some(BUTTON_FILL_COLOR_RIGHT));
+            if choice != self.right_answer {
+                self.answers[choice].set_fill_color(Some(BUTTON_FILL_COLOR_WRONG));
+            }
+        }
+        self.status
+    }
+
+    pub fn typed_text(&self) -> Option<&str> {
+        self.entry.as_ref().map(|e| e.text())
+    }
+
+    //These return true if the texture needs updating.
+    pub fn type_char(&mut self, c: char, fonts: &Fonts) -> bool {
+        match self.entry {
+            Some(ref mut entry) => entry.push(c, fonts),
+            None => false,
+        }
+    }
+
+    pub fn backspace(&mut self, fonts: &Fonts) -> bool {
+        match self.entry {
+            Some(ref mut entry) => entry.pop(fonts),
+            None => false,
+        }
+    }
+
+    //Marks a typed answer. Like choose, only the first one counts.
+    pub fn submit(&mut self, right: bool, fonts: &Fonts) -> QuestionStatus {
+        if self.status == QuestionStatus::Untouched {
+            if let Some(ref mut entry) = self.entry {
+                let (status, color) = if right {
+                    (QuestionStatus::Right, BUTTON_FILL_COLOR_RIGHT)
+                } else {
+                    (QuestionStatus::Wrong, BUTTON_FILL_COLOR_WRONG)
+                };
+                self.status = status;
+                entry.finish(color, fonts);
+            }
+        }
+        self.status
+    }
+
+    pub fn is_answered(&self) -> bool {
+        self.status != QuestionStatus::Untouched
+    }
+
+    pub fn continue_clicked(&self) -> bool {
+        self.is_answered() && self.continue_button.is_clicked()
+    }
+
+    fn icon_position(&self, choice: usize) -> (f32, f32) {
+        let b = &self.answers[choice];
+        (
+            b.position.0 + b.width() + BUTTON_SPACING,
+            b.position.1 + (b.height() - self.right_icon.required_height()) / 2.0,
+        )
     }
 }
 
-impl Question {
-    fn new(question: String, correct_answer: String, incorrect_answers: Vec<String>, texture_offset: (f32,f32), font: &Font) -> Question{
-        let question_image = render_text(font,15.0,question.as_str());
-        let correct_image = render_text(font,15.0,correct_answer.as_str());
-        let mut wrong_images: Vec<RgbaImage> = Vec::new();
-        for s in incorrect_answers {
-            wrong_images.push( render_text(font,15.0,s.as_str()));
-        }
-        let mut question =  Question {
-            question: question_image,
-            correct_answer: correct_image,
-            wrong_answers: Vec<TexturedButton>,
-            status: QuestionStatus,
-        };
+impl Renderable for Question {
+    fn render(&self, verts: &mut Vec<GLfloat>, window_size: (f32, f32), texture_size: (f32, f32)) {
+        add_textured_box(
+            self.position,
+            TEXT_LAYER,
+            self.question_offset,
+            TEXT_COLOR,
+            (self.question.width() as f32, self.question.height() as f32),
+            window_size,
+            texture_size,
+            verts,
+        );
+        for b in self.answers.iter() {
+            b.render(verts, window_size, texture_size);
+        }
+
+        if let Some(ref entry) = self.entry {
+            entry.render(verts, window_size, texture_size);
+            if self.is_answered() {
+                let icon = if self.status == QuestionStatus::Right {
+                    &self.right_icon
+                } else {
+                    &self.wrong_icon
+                };
+                let icon_position = (
+                    entry.position.0 + entry.width() + BUTTON_SPACING,
+                    entry.position.1 + (entry.height() - icon.required_height()) / 2.0,
+                );
+                icon.render_at(icon_position, verts, window_size, texture_size);
+                self.continue_button
+                    .render(verts, window_size, texture_size);
+            }
+        }
+        if let Some(ref reveal) = self.reveal {
+            if self.status == QuestionStatus::Wrong {
+                add_textured_box(
+                    self.reveal_position(),
+                    TEXT_LAYER,
+                    self.reveal_offset,
+                    TEXT_COLOR,
+                    (reveal.width() as f32, reveal.height() as f32),
+                    window_size,
+                    texture_size,
+                    verts,
+                );
+            }
+        }
+
+        if let Some(ref explanation) = self.explanation {
+            if self.is_answered() {
+                add_textured_box(
+                    self.explanation_position(),
+                    TEXT_LAYER,
+                    self.explanation_offset,
+                    TEXT_COLOR,
+                    (explanation.width() as f32, explanation.height() as f32),
+                    window_size,
+                    texture_size,
+                    verts,
+                );
+            }
+        }
+
+        if let Some(choice) = self.chosen {
+            let right_position = self.icon_position(self.right_answer);
+            self.right_icon
+                .render_at(right_position, verts, window_size, texture_size);
+            if choice != self.right_answer {
+                let wrong_position = self.icon_position(choice);
+                self.wrong_icon
+                    .render_at(wrong_position, verts, window_size, texture_size);
+            }
+            self.continue_button
+                .render(verts, window_size, texture_size);
+        }
+    }
+    fn width(&self) -> f32 {
+        let explanation_width = self.explanation.as_ref().map_or(0, |e| e.width()) as f32;
+        (self.question.width() as f32)
+            .max(self.answers_size().0)
+            .max(explanation_width)
+    }
+    fn height(&self) -> f32 {
+        self.question.height() as f32
+            + BUTTON_SPACING
+            + self.answers_size().1
+            + self.explanation_height()
+            + self.continue_button.height()
     }
 }
 
+//Everything is stacked in one column of the texture.
 impl TextureUser for Question {
     fn required_width(&self) -> f32 {
-        let mut width = self.correct_answer.required_width();
-        for b in self.wrong_answers.iter() {
-            width += b.required_width();
+        let mut width = self.question.width() as f32;
+        for b in self.answers.iter() {
+            width = width.max(b.required_width());
+        }
+        if let Some(ref entry) = self.entry {
+            width = width.max(entry.required_width());
+        }
+        if let Some(ref reveal) = self.reveal {
+            width = width.max(reveal.width() as f32);
+        }
+        if let Some(ref explanation) = self.explanation {
+            width = width.max(explanation.width() as f32);
         }
         width
+            .max(self.right_icon.required_width())
+            .max(self.wrong_icon.required_width())
+            .max(self.continue_button.required_width())
     }
     fn required_height(&self) -> f32 {
-        let mut height = self.correct_answer.required_height();
-        for b in self.wrong_answers.iter() {
-            height += height.max(b.required_height());
+        let mut height = self.question.height() as f32;
+        for b in self.answers.iter() {
+            height += b.required_height();
+        }
+        if let Some(ref entry) = self.entry {
+            height += entry.required_height();
+        }
+        if let Some(ref reveal) = self.reveal {
+            height += reveal.height() as f32;
+        }
+        if let Some(ref explanation) = self.explanation {
+            height += explanation.height() as f32;
         }
         height
-    }
-
-    fn required_size(&self) -> (f32, f32) {
-        (self.required_width(), self.required_height())
+            + self.right_icon.required_height()
+            + self.wrong_icon.required_height()
+            + self.continue_button.required_height()
     }
 
     fn set_offset(&mut self, offset: (f32, f32)) {
-        let mut x_offset = offset.0;
-        self.correct_answer.set_offset((x_offset,offset.1));
-        x_offset += self.correct_answer.required_width();
-        for b in wrong_answers.iter() {
-            self.correct_answer.set_offset((x_offset,offset.1));
-            x_offset += self.correct_answer.required_width();
+        self.question_offset = offset;
+        let mut y_offset = offset.1 + self.question.height() as f32;
+        for b in self.answers.iter_mut() {
+            b.set_offset((offset.0, y_offset));
+            y_offset += b.required_height();
         }
-    }
-    fn get_offset(&self) -> (f32, f32) {
-        self.correct_answer.get_offset()
+        if let Some(ref mut entry) = self.entry {
+            entry.set_offset((offset.0, y_offset));
+            y_offset += entry.required_height();
+        }
+        if let Some(ref reveal) = self.reveal {
+            self.reveal_offset = (offset.0, y_offset);
+            y_offset += reveal.height() as f32;
+        }
+        if let Some(ref explanation) = self.explanation {
+            self.explanation_offset = (offset.0, y_offset);
+            y_offset += explanation.height() as f32;
+        }
+        self.right_icon.set_offset((offset.0, y_offset));
+        y_offset += self.right_icon.required_height();
+        self.wrong_icon.set_offset((offset.0, y_offset));
+        y_offset += self.wrong_icon.required_height();
+        self.continue_button.set_offset((offset.0, y_offset));
     }
 
     fn update_texture(&self, texture: &mut RgbaImage) {
-        correct_answer.update_texture(texture);
-        for b in wrong_answers.iter() {
+        copy_into_texture(&self.question, self.question_offset, texture);
+        for b in self.answers.iter() {
             b.update_texture(texture);
         }
+        if let Some(ref entry) = self.entry {
+            entry.update_texture(texture);
+        }
+        if let Some(ref reveal) = self.reveal {
+            copy_into_texture(reveal, self.reveal_offset, texture);
+        }
+        if let Some(ref explanation) = self.explanation {
+            copy_into_texture(explanation, self.explanation_offset, texture);
+        }
+        self.right_icon.update_texture(texture);
+        self.wrong_icon.update_texture(texture);
+        self.continue_button.update_texture(texture);
     }
 }
 
 impl Clickable for Question {
     fn update_state(&mut self, mouse_pos: (f32, f32), button_pressed: bool) {
-        self.correct_answer.update_state(mouse_pos, button_pressed);
-        for b in self.wrong_answers.iter() {
-            b.update_state(mouse_pos, button_pressed);
-            if b.is_clicked() {
-                self.status = QuestionStatus::wrong;
+        if self.is_answered() {
+            self.continue_button.update_state(mouse_pos, button_pressed);
+        } else {
+            for b in self.answers.iter_mut() {
+                b.update_state(mouse_pos, button_pressed);
             }
         }
-        if self.correct_answer.is_clicked() {
-            self.status = QuestionStatus::right;
-        }
     }
 
     fn is_clicked(&self) -> bool {
-        false
+        self.clicked_answer().is_some() || self.continue_clicked()
     }
 }
@@ -0,0 +1,38 @@
+extern crate csv;
+extern crate image;
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod check;
+pub mod deck;
+pub mod error;
+pub mod format;
+pub mod markup;
+pub mod matching;
+pub mod question;
+pub mod results;
+pub mod schedule;
+pub mod session;
+
+pub use check::{check_quiz, Problem, ProblemKind, Report};
+pub use deck::{
+    open_quiz_file, read_header, read_slides, resolve_image_path, split_answer, Column, Header,
+    Metadata, Quiz, Slide, ALIAS_SEPARATOR,
+};
+pub use error::{Location, QuizLoadError};
+pub use format::{slide_location, write_quiz, DeckFormat};
+pub use markup::{parse_emphasis, Style, StyledChar};
+pub use matching::{edit_distance, matches_answer, normalize, DEFAULT_TOLERANCE};
+pub use question::{
+    generate_cross_question, generate_question, generate_reverse_question, AnswerMode,
+    CrossCategories, Question, DEFAULT_CHOICES, MAX_CHOICES, MIN_CHOICES,
+};
+pub use results::{summary_path, write_results, SessionResults};
+pub use schedule::{today, CardProgress, Schedule};
+pub use session::{
+    CategoryScore, Guess, MissedSlide, Progress, QuestionResult, Review, Score, Session,
+};
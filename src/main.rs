@@ -1,30 +1,38 @@
-extern crate csv;
 extern crate gl;
 extern crate glfw;
 extern crate image;
-extern crate rand;
 extern crate rusttype;
+extern crate rusty_quiz;
+extern crate serde_json;
 
+mod atlas;
+mod gui_stuff;
+mod resources;
+mod text;
+
+use atlas::{Region, TextureAtlas, MAX_ATLAS_SIZE};
 use gl::types::*;
-use glfw::{Action, Context, Key};
-use image::{Rgba, RgbaImage};
-use rand::{thread_rng, Rng};
-use rusttype::{point, Font, Scale};
+use glfw::{Action, Context, Key, MouseButton, WindowEvent};
+use gui_stuff::{Clickable, Renderable, TextureUser};
+use image::RgbaImage;
+use rusttype::Font;
+use rusty_quiz::{
+    check_quiz, slide_location, today, write_quiz, write_results, AnswerMode, CrossCategories,
+    DeckFormat, Guess, Header, Progress, Quiz, Report, Schedule, Score, Session, SessionResults,
+    Slide, MAX_CHOICES, MIN_CHOICES,
+};
+use std::collections::HashMap;
 use std::env;
-use std::io::Read;
-
-struct Header {
-    number_of_hints: i32,
-    questions: Vec<String>,
-}
-
-struct Slide {
-    image: Option<RgbaImage>,
-    hints: Vec<String>,
-    answers: Vec<String>,
-}
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use text::{
+    draw_layout, layout_markup, layout_text, missing_glyphs, render_layout, Alignment, Fonts,
+    GlyphCache, TextLayout,
+};
 
 //All function parameters are expected to be in pixels.
+#[allow(clippy::too_many_arguments)]
 fn add_textured_box(
     window_position: (f32, f32),
     layer: f32,
@@ -34,6 +42,32 @@ fn add_textured_box(
     window_size: (f32, f32),
     texture_size: (f32, f32),
     verts: &mut Vec<GLfloat>,
+) {
+    add_stretched_box(
+        window_position,
+        text_size,
+        layer,
+        texture_offset,
+        text_size,
+        color,
+        window_size,
+        texture_size,
+        verts,
+    );
+}
+
+//Like add_textured_box, with the part of the texture stretched to `box_size` in the window.
+#[allow(clippy::too_many_arguments)]
+fn add_stretched_box(
+    window_position: (f32, f32),
+    box_size: (f32, f32),
+    layer: f32,
+    texture_offset: (f32, f32),
+    text_size: (f32, f32),
+    color: (f32, f32, f32),
+    window_size: (f32, f32),
+    texture_size: (f32, f32),
+    verts: &mut Vec<GLfloat>,
 ) {
     let pos = (
         (window_position.0 * 2.0) / window_size.0 - 1.0,
@@ -44,8 +78,8 @@ fn add_textured_box(
         texture_offset.1 / texture_size.1,
     );
     let box_size = (
-        (text_size.0 * 2.0) / window_size.0,
-        (text_size.1 * -2.0) / window_size.1,
+        (box_size.0 * 2.0) / window_size.0,
+        (box_size.1 * -2.0) / window_size.1,
     );
     let text_size = (text_size.0 / texture_size.0, text_size.1 / texture_size.1);
 
@@ -136,6 +170,48 @@ fn add_textured_box(
     verts.push(1.0);
 }
 
+fn add_colored_box(
+    window_position: (f32, f32),
+    layer: f32,
+    color: (f32, f32, f32),
+    box_size: (f32, f32),
+    window_size: (f32, f32),
+    verts: &mut Vec<GLfloat>,
+) {
+    let pos = (
+        (window_position.0 * 2.0) / window_size.0 - 1.0,
+        (window_position.1 * -2.0) / window_size.1 + 1.0,
+    );
+    let box_size = (
+        (box_size.0 * 2.0) / window_size.0,
+        (box_size.1 * -2.0) / window_size.1,
+    );
+
+    let corners = [
+        (pos.0, pos.1),
+        (pos.0 + box_size.0, pos.1),
+        (pos.0 + box_size.0, pos.1 + box_size.1),
+        (pos.0, pos.1),
+        (pos.0, pos.1 + box_size.1),
+        (pos.0 + box_size.0, pos.1 + box_size.1),
+    ];
+    for corner in corners.iter() {
+        //pos
+        verts.push(corner.0);
+        verts.push(corner.1);
+        verts.push(layer);
+        //color
+        verts.push(color.0);
+        verts.push(color.1);
+        verts.push(color.2);
+        //tex coord
+        verts.push(0.0);
+        verts.push(0.0);
+        //enable texture
+        verts.push(0.0);
+    }
+}
+
 fn print_gl_error() {
     println!(
         "{}",
@@ -152,8 +228,7 @@ fn print_gl_error() {
 }
 
 fn print_shader_log(shader: GLuint) {
-    let mut program_log: Vec<u8> = Vec::new();
-    program_log.resize(10000, 0);
+    let mut program_log: Vec<u8> = vec![0; 10000];
     let mut log_size: GLsizei = 0;
     unsafe {
         gl::GetShaderInfoLog(
@@ -169,26 +244,18 @@ fn print_shader_log(shader: GLuint) {
     println!("{}", std::string::String::from_utf8(program_log).unwrap())
 }
 
-unsafe fn setup_shaders(vert_src: &String, frag_src: &String) {
+unsafe fn setup_shaders(vert_src: &str, frag_src: &str) {
     let vert_shader = gl::CreateShader(gl::VERTEX_SHADER);
     let frag_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
     {
-        let s = std::mem::transmute::<&u8, *const GLchar>(&vert_src.as_bytes()[0]);
-        gl::ShaderSource(
-            vert_shader,
-            1,
-            &s as *const *const GLchar,
-            std::mem::transmute::<&usize, *const GLint>(&vert_src.len()),
-        );
+        let s = vert_src.as_ptr() as *const GLchar;
+        let len = vert_src.len() as GLint;
+        gl::ShaderSource(vert_shader, 1, &s, &len);
     }
     {
-        let s = std::mem::transmute::<&u8, *const GLchar>(&frag_src.as_bytes()[0]);
-        gl::ShaderSource(
-            frag_shader,
-            1,
-            &s as *const *const GLchar,
-            std::mem::transmute::<&usize, *const GLint>(&frag_src.len()),
-        );
+        let s = frag_src.as_ptr() as *const GLchar;
+        let len = frag_src.len() as GLint;
+        gl::ShaderSource(frag_shader, 1, &s, &len);
     }
     gl::CompileShader(vert_shader);
     gl::CompileShader(frag_shader);
@@ -205,31 +272,35 @@ unsafe fn setup_shaders(vert_src: &String, frag_src: &String) {
     gl::UseProgram(shader);
 }
 
-unsafe fn texture_setup(texture: &mut GLuint) {
+//Mipmaps make slide images that are shrunk a lot look smoother.
+unsafe fn texture_setup(texture: &mut GLuint, mipmaps: bool) {
     gl::GenTextures(1, texture);
     gl::BindTexture(gl::TEXTURE_2D, *texture);
 
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-}
-
-unsafe fn set_texture_data(image: &RgbaImage) {
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        gl::RGBA as i32,
-        image.width() as i32,
-        image.height() as i32,
-        0,
-        gl::RGBA,
-        gl::UNSIGNED_BYTE,
-        image.as_ptr() as *const std::os::raw::c_void,
-    );
+    //Things in the atlas sit next to each other, so nothing should wrap around to the other side of it.
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    let min_filter = if mipmaps {
+        gl::LINEAR_MIPMAP_LINEAR
+    } else {
+        gl::LINEAR
+    };
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
 }
 
-unsafe fn set_vertex_data(verts: &Vec<GLfloat>) {
+fn window_size(window: &glfw::Window) -> (f32, f32) {
+    let size = window.get_size();
+    (size.0 as f32, size.1 as f32)
+}
+
+unsafe fn max_texture_size() -> u32 {
+    let mut size: GLint = 0;
+    gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut size);
+    size as u32
+}
+
+unsafe fn set_vertex_data(verts: &[GLfloat]) {
     gl::BufferData(
         gl::ARRAY_BUFFER,
         4 * verts.len() as isize,
@@ -267,7 +338,7 @@ unsafe fn vertex_buffer_setup(vert_buffer: &mut GLuint) {
         gl::FLOAT,
         gl::FALSE,
         9 * 4,
-        std::mem::transmute::<u64, *const std::os::raw::c_void>(3 * 4),
+        (3 * 4) as *const std::os::raw::c_void,
     );
     gl::VertexAttribPointer(
         2,
@@ -275,7 +346,7 @@ unsafe fn vertex_buffer_setup(vert_buffer: &mut GLuint) {
         gl::FLOAT,
         gl::FALSE,
         9 * 4,
-        std::mem::transmute::<u64, *const std::os::raw::c_void>(6 * 4),
+        (6 * 4) as *const std::os::raw::c_void,
     );
     gl::VertexAttribPointer(
         3,
@@ -283,228 +354,1027 @@ unsafe fn vertex_buffer_setup(vert_buffer: &mut GLuint) {
         gl::FLOAT,
         gl::FALSE,
         9 * 4,
-        std::mem::transmute::<u64, *const std::os::raw::c_void>(8 * 4),
+        (8 * 4) as *const std::os::raw::c_void,
     );
 }
 
-fn render_text(font: &Font, scale: f32, text: &str) -> RgbaImage {
-    let scale = Scale::uniform(scale);
+fn copy_image_into_image(
+    source_image: &RgbaImage,
+    offset: (u32, u32),
+    destination_image: &mut RgbaImage,
+) {
+    for (x, y, pixel) in source_image.enumerate_pixels() {
+        destination_image.put_pixel(offset.0 + x, offset.1 + y, *pixel);
+    }
+}
 
-    let v_metrics = font.v_metrics(scale);
+const TEXT_COLOR: (f32, f32, f32) = (0.1, 0.1, 0.1);
+const HINT_TEXT_SIZE: f32 = 20.0;
+//Longer hints are wrapped onto more lines.
+const HINT_MAX_WIDTH: f32 = 500.0;
+const QUESTION_MARGIN: f32 = 10.0;
+//Reverse questions show each slide shrunk down to fit in this.
+const THUMBNAIL_SIZE: (u32, u32) = (240, 160);
+
+//The score and timer in the bottom right corner.
+const HUD_TEXT_SIZE: f32 = 18.0;
+
+//Laid out text and where it gets drawn in the window.
+struct TextRun {
+    layout: TextLayout,
+    position: (f32, f32),
+}
 
-    let glyphs: Vec<_> = font
-        .layout(text, scale, point(10.0, 10.0 + v_metrics.ascent))
-        .collect();
+//Slide images never get smaller than this, even if it pushes the question out of the window.
+const MIN_IMAGE_AREA: (f32, f32) = (100.0, 100.0);
+//Fills the part of the image's area the image doesn't.
+const LETTERBOX_COLOR: (f32, f32, f32) = (0.2, 0.2, 0.2);
+
+//The area set aside for the slide image and the part of it the image is drawn in.
+#[derive(Clone, Copy)]
+struct ImageBox {
+    area: (f32, f32),
+    position: (f32, f32),
+    size: (f32, f32),
+}
 
-    let glyphs_height = (v_metrics.ascent - v_metrics.descent).ceil() as u32;
-    let glyphs_width = {
-        let min_x = glyphs
-            .first()
-            .map(|g| g.pixel_bounding_box().unwrap().min.x)
-            .unwrap();
-        let max_x = glyphs
-            .last()
-            .map(|g| g.pixel_bounding_box().unwrap().max.x)
-            .unwrap();
-        (max_x - min_x) as u32
-    };
+//As big as it can be inside the area without changing its shape, and centered.
+fn fit_image(image_size: (u32, u32), area: (f32, f32)) -> ImageBox {
+    let scale = (area.0 / image_size.0 as f32).min(area.1 / image_size.1 as f32);
+    let size = (
+        (image_size.0 as f32 * scale).round(),
+        (image_size.1 as f32 * scale).round(),
+    );
+    ImageBox {
+        area,
+        position: (
+            ((area.0 - size.0) / 2.0).floor(),
+            ((area.1 - size.1) / 2.0).floor(),
+        ),
+        size,
+    }
+}
+
+//The slide image sits in the top left with the hints to its right.
+//Anything shown along with the slide goes under both, from `bottom` down.
+struct SlideLayout {
+    image: Option<ImageBox>,
+    hints: Vec<TextRun>,
+    bottom: f32,
+}
+
+fn layout_hints(header: &Header, slide: &Slide, fonts: &Fonts) -> Vec<TextLayout> {
+    header
+        .hint_text(slide)
+        .iter()
+        .map(|h| {
+            layout_markup(
+                fonts,
+                HINT_TEXT_SIZE,
+                h,
+                Some(HINT_MAX_WIDTH),
+                Alignment::Left,
+            )
+        })
+        .collect()
+}
+
+//The image is fitted into `image_area`, which is left out if there is no image.
+fn layout_slide(slide: &Slide, hints: Vec<TextLayout>, image_area: (f32, f32)) -> SlideLayout {
+    let image = slide
+        .image
+        .as_ref()
+        .map(|i| fit_image((i.width(), i.height()), image_area));
+    let area = image.map_or((0.0, 0.0), |i| i.area);
+
+    let mut hint_runs = Vec::new();
+    let mut top = 0.0;
+    for layout in hints {
+        let height = layout.height as f32;
+        hint_runs.push(TextRun {
+            layout,
+            position: (area.0, top),
+        });
+        top += height;
+    }
+    SlideLayout {
+        image,
+        hints: hint_runs,
+        bottom: area.1.max(top),
+    }
+}
 
-    let mut image = RgbaImage::new(glyphs_width + 20, glyphs_height + 20);
+//Everything in the window besides the question, which keeps its own part of the atlas.
+struct Screen<'a> {
+    slide: Option<&'a Slide>,
+    image: Option<ImageBox>,
+    texts: Vec<TextRun>,
+}
 
-    for glyph in glyphs {
-        if let Some(bounding_box) = glyph.pixel_bounding_box() {
-            glyph.draw(|x, y, v| {
-                image.put_pixel(
-                    x + bounding_box.min.x as u32,
-                    y + bounding_box.min.y as u32,
-                    Rgba {
-                        data: [255, 255, 255, (v * 255.0) as u8],
-                    },
-                )
-            });
+//The question goes under the slide, or at the top when there is no slide to show.
+//The slide image gets whatever room the hints and the question leave.
+fn question_screen<'a>(
+    header: &Header,
+    slide: Option<&'a Slide>,
+    question: &mut gui_stuff::Question,
+    fonts: &Fonts,
+    window_size: (f32, f32),
+) -> Screen<'a> {
+    match slide {
+        Some(slide) => {
+            let hints = layout_hints(header, slide, fonts);
+            let hints_width = hints.iter().map(|h| h.width).max().unwrap_or(0) as f32;
+            let image_area = (
+                (window_size.0 - hints_width).max(MIN_IMAGE_AREA.0),
+                (window_size.1 - QUESTION_MARGIN - question.height()).max(MIN_IMAGE_AREA.1),
+            );
+            let layout = layout_slide(slide, hints, image_area);
+            question.set_position((0.0, layout.bottom + QUESTION_MARGIN));
+            Screen {
+                slide: Some(slide),
+                image: layout.image,
+                texts: layout.hints,
+            }
+        }
+        None => {
+            question.set_position((0.0, 0.0));
+            Screen {
+                slide: None,
+                image: None,
+                texts: Vec::new(),
+            }
         }
     }
+}
 
-    image
+//The slide laid out like it is when asked about, shrunk to fit THUMBNAIL_SIZE.
+fn slide_thumbnail(header: &Header, slide: &Slide, fonts: &Fonts) -> RgbaImage {
+    let size = slide
+        .image
+        .as_ref()
+        .map_or((0, 0), |i| (i.width(), i.height()));
+    let layout = layout_slide(
+        slide,
+        layout_hints(header, slide, fonts),
+        (size.0 as f32, size.1 as f32),
+    );
+    let width = layout
+        .hints
+        .iter()
+        .map(|h| h.position.0 as u32 + h.layout.width)
+        .max()
+        .unwrap_or(0)
+        .max(size.0);
+    let mut image = RgbaImage::new(width, layout.bottom as u32);
+
+    if let Some(ref slide_image) = slide.image {
+        copy_image_into_image(slide_image, (0, 0), &mut image);
+    }
+    for hint in layout.hints.iter() {
+        let text = render_layout(fonts, &hint.layout);
+        for (x, y, pixel) in text.enumerate_pixels() {
+            let mut pixel = *pixel;
+            pixel.data[0] = (pixel.data[0] as f32 * TEXT_COLOR.0) as u8;
+            pixel.data[1] = (pixel.data[1] as f32 * TEXT_COLOR.1) as u8;
+            pixel.data[2] = (pixel.data[2] as f32 * TEXT_COLOR.2) as u8;
+            image.put_pixel(
+                hint.position.0 as u32 + x,
+                hint.position.1 as u32 + y,
+                pixel,
+            );
+        }
+    }
+
+    let scale = (THUMBNAIL_SIZE.0 as f32 / image.width().max(1) as f32)
+        .min(THUMBNAIL_SIZE.1 as f32 / image.height().max(1) as f32);
+    if scale >= 1.0 {
+        return image;
+    }
+    image::imageops::resize(
+        &image,
+        ((image.width() as f32 * scale) as u32).max(1),
+        ((image.height() as f32 * scale) as u32).max(1),
+        image::FilterType::Triangle,
+    )
 }
 
-fn copy_image_into_image(
-    source_image: &RgbaImage,
-    offset: (u32, u32),
-    destination_image: &mut RgbaImage,
-) {
-    for (x, y, pixel) in source_image.enumerate_pixels() {
-        destination_image.put_pixel(offset.0 + x, offset.1 + y, *pixel);
+//The textures everything in the window is drawn from, and what has been put in the atlas.
+struct Textures {
+    atlas: TextureAtlas,
+    atlas_texture: GLuint,
+    slide_texture: Option<SlideTexture>,
+    glyphs: GlyphCache,
+    //By line, which is different for every slide in a quiz, and the size the image was shrunk to.
+    slide_images: HashMap<(u64, u32, u32), Region>,
+    slides_generation: u32,
+    //Where the current question was drawn, and in which generation of the atlas.
+    question_region: Option<(u32, Region)>,
+    //Only said once, rather than every frame.
+    warned_atlas_full: bool,
+}
+
+//Slide images take up at most this much of the atlas across and down, so there is room left for the question and glyphs.
+const MAX_IMAGE_FRACTION: f32 = 0.5;
+
+//With --mipmaps the slide image gets a texture of its own at full size.
+//Mipmaps of the atlas would blend everything in it into its neighbours, and would have to be made again whenever anything in it changed.
+struct SlideTexture {
+    texture: GLuint,
+    //The line of the slide whose image is in it.
+    slide: Option<u64>,
+    size: (f32, f32),
+}
+
+impl SlideTexture {
+    //Only uploads the image when the slide changes. Images bigger than the GPU can take are shrunk to fit.
+    unsafe fn show(&mut self, slide: &Slide, image: &RgbaImage, atlas_texture: GLuint) {
+        if self.slide == Some(slide.line) {
+            return;
+        }
+        let largest = max_texture_size() as f32;
+        let scale = (largest / image.width() as f32)
+            .min(largest / image.height() as f32)
+            .min(1.0);
+        let resized;
+        let image = if scale < 1.0 {
+            resized = image::imageops::resize(
+                image,
+                ((image.width() as f32 * scale) as u32).max(1),
+                ((image.height() as f32 * scale) as u32).max(1),
+                image::FilterType::Triangle,
+            );
+            &resized
+        } else {
+            image
+        };
+
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as GLint,
+            image.width() as GLint,
+            image.height() as GLint,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image.as_ptr() as *const std::os::raw::c_void,
+        );
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+        gl::BindTexture(gl::TEXTURE_2D, atlas_texture);
+
+        self.slide = Some(slide.line);
+        self.size = (image.width() as f32, image.height() as f32);
+    }
+}
+
+//The slide image is drawn from its own texture when it has one, and everything else from the atlas.
+struct Frame {
+    slide_verts: Vec<GLfloat>,
+    verts: Vec<GLfloat>,
+}
+
+unsafe fn draw_frame(frame: &Frame, textures: &mut Textures) {
+    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    if let Some(ref slide_texture) = textures.slide_texture {
+        if !frame.slide_verts.is_empty() {
+            gl::BindTexture(gl::TEXTURE_2D, slide_texture.texture);
+            set_vertex_data(&frame.slide_verts);
+            gl::DrawArrays(gl::TRIANGLES, 0, (frame.slide_verts.len() / 9) as i32);
+            gl::BindTexture(gl::TEXTURE_2D, textures.atlas_texture);
+        }
     }
+    textures.atlas.upload();
+    set_vertex_data(&frame.verts);
+    gl::DrawArrays(gl::TRIANGLES, 0, (frame.verts.len() / 9) as i32);
 }
 
-fn read_slides(csv_reader: &mut csv::Reader<std::fs::File>, header: &Header) -> Vec<Slide> {
-    let mut slides: Vec<Slide> = Vec::new();
+impl Textures {
+    fn new(
+        atlas: TextureAtlas,
+        atlas_texture: GLuint,
+        slide_texture: Option<SlideTexture>,
+    ) -> Textures {
+        Textures {
+            slides_generation: atlas.generation(),
+            atlas,
+            atlas_texture,
+            slide_texture,
+            glyphs: GlyphCache::new(),
+            slide_images: HashMap::new(),
+            question_region: None,
+            warned_atlas_full: false,
+        }
+    }
 
-    for record in csv_reader.records() {
-        let record = record.unwrap();
+    //The image is shrunk to the size it is shown at, so big photos don't take up the whole atlas.
+    //It is never made bigger than it is, or than MAX_IMAGE_FRACTION of the atlas. Err if the atlas is full.
+    fn slide_image(
+        &mut self,
+        slide: &Slide,
+        image: &RgbaImage,
+        shown_size: (f32, f32),
+    ) -> Result<Region, ()> {
+        if self.slides_generation != self.atlas.generation() {
+            self.slide_images.clear();
+            self.slides_generation = self.atlas.generation();
+        }
+
+        let (atlas_size, _) = self.atlas.size();
+        let largest = atlas_size * MAX_IMAGE_FRACTION;
+        let scale = (shown_size.0 / image.width() as f32)
+            .min(largest / image.width() as f32)
+            .min(largest / image.height() as f32)
+            .min(1.0);
+        let size = (
+            ((image.width() as f32 * scale).round() as u32).max(1),
+            ((image.height() as f32 * scale).round() as u32).max(1),
+        );
+        let key = (slide.line, size.0, size.1);
+        if let Some(&region) = self.slide_images.get(&key) {
+            return Ok(region);
+        }
 
-        let mut slide = Slide {
-            image: None,
-            hints: Vec::new(),
-            answers: Vec::new(),
+        let region = if size == image.dimensions() {
+            self.atlas.insert(image)
+        } else {
+            self.atlas.insert(&image::imageops::resize(
+                image,
+                size.0,
+                size.1,
+                image::FilterType::Triangle,
+            ))
         };
+        let region = region.ok_or(())?;
+        self.slide_images.insert(key, region);
+        Ok(region)
+    }
 
-        let mut i = 0;
-        for value in record.iter() {
-            let value = value.to_string();
-            match i {
-                0 => {
-                    if !value.is_empty() {
-                        slide.image = Some(image::open(&value).expect(value.as_str()).to_rgba());
-                    }
-                }
-                _ if i <= header.number_of_hints => {
-                    slide.hints.push(value);
-                }
-                _ => {
-                    slide.answers.push(value);
-                }
+    fn warn_atlas_full(&mut self, consequence: &str) {
+        if !self.warned_atlas_full {
+            eprintln!(
+                "Not everything on screen fits in the texture atlas, so {}.",
+                consequence
+            );
+            self.warned_atlas_full = true;
+        }
+    }
+
+    //A new question needs new_question before it is drawn.
+    fn new_question(&mut self) {
+        self.question_region = None;
+    }
+
+    //Draws the question into the atlas if it isn't in this generation of it. Err if the atlas is full.
+    fn add_question(&mut self, question: &mut gui_stuff::Question) -> Result<(), ()> {
+        match self.question_region {
+            Some((generation, _)) if generation == self.atlas.generation() => Ok(()),
+            _ => {
+                let size = question.required_size();
+                let region = self
+                    .atlas
+                    .allocate(size.0.ceil() as u32, size.1.ceil() as u32)
+                    .ok_or(())?;
+                question.set_offset(region.offset());
+                self.atlas
+                    .update(region, |texture| question.update_texture(texture));
+                self.question_region = Some((self.atlas.generation(), region));
+                Ok(())
             }
-            i += 1;
         }
-        slides.push(slide);
     }
 
-    slides
+    //After the question changes how it looks.
+    fn redraw_question(&mut self, question: &gui_stuff::Question) {
+        if let Some((generation, region)) = self.question_region {
+            if generation == self.atlas.generation() {
+                self.atlas
+                    .update(region, |texture| question.update_texture(texture));
+            }
+        }
+    }
 }
 
-fn read_header(csv_reader: &mut csv::Reader<std::fs::File>) -> Header {
-    let mut header = Header {
-        number_of_hints: 0,
-        questions: Vec::new(),
+//Adds everything in the window to verts, drawing whatever isn't in the atlas yet into it. Err if the atlas is full.
+#[allow(clippy::too_many_arguments)]
+fn add_screen(
+    screen: &Screen,
+    question: Option<&mut gui_stuff::Question>,
+    hud: Option<&TextRun>,
+    with_image: bool,
+    textures: &mut Textures,
+    fonts: &Fonts,
+    window_size: (f32, f32),
+    frame: &mut Frame,
+) -> Result<(), ()> {
+    let verts = &mut frame.verts;
+    let image = match (screen.slide, screen.image) {
+        (Some(slide), Some(image_box)) => slide.image.as_ref().map(|i| (slide, i, image_box)),
+        _ => None,
     };
+    if let Some((slide, image, image_box)) = image {
+        add_colored_box(
+            (0.0, 0.0),
+            0.1,
+            LETTERBOX_COLOR,
+            image_box.area,
+            window_size,
+            verts,
+        );
+        if let Some(ref mut slide_texture) = textures.slide_texture {
+            unsafe {
+                slide_texture.show(slide, image, textures.atlas_texture);
+            }
+            add_stretched_box(
+                image_box.position,
+                image_box.size,
+                0.0,
+                (0.0, 0.0),
+                slide_texture.size,
+                (1.0, 1.0, 1.0),
+                window_size,
+                slide_texture.size,
+                &mut frame.slide_verts,
+            );
+        } else if with_image {
+            let region = textures.slide_image(slide, image, image_box.size)?;
+            //Half a pixel is left off each edge, so the gap around it in the atlas isn't blended in.
+            add_stretched_box(
+                image_box.position,
+                image_box.size,
+                0.0,
+                (region.x as f32 + 0.5, region.y as f32 + 0.5),
+                (region.width as f32 - 1.0, region.height as f32 - 1.0),
+                (1.0, 1.0, 1.0),
+                window_size,
+                textures.atlas.size(),
+                verts,
+            );
+        }
+    }
 
-    //It expects the first item to be "image." Hints are expected to only be placed immedently after "image." Everything after hints is expected to be a question.
-    for header_item in csv_reader.headers().unwrap().iter() {
-        let header_item = header_item.to_string();
-        match header_item.as_str() {
-            "hint" => header.number_of_hints += 1,
-            "image" => (),
-            _ => {
-                header.questions.push(header_item);
+    for text in screen.texts.iter().chain(hud) {
+        draw_layout(
+            fonts,
+            &text.layout,
+            text.position,
+            TEXT_COLOR,
+            &mut textures.glyphs,
+            &mut textures.atlas,
+            window_size,
+            verts,
+        )?;
+    }
+
+    if let Some(question) = question {
+        textures.add_question(question)?;
+        question.render(verts, window_size, textures.atlas.size());
+    }
+    Ok(())
+}
+
+//When the atlas fills up it is cleared and filled again with only what is on screen now.
+//If that doesn't fit either, the slide image is left out, and if it still doesn't fit whatever did is drawn.
+fn screen_verts(
+    screen: &Screen,
+    mut question: Option<&mut gui_stuff::Question>,
+    hud: Option<&TextRun>,
+    textures: &mut Textures,
+    fonts: &Fonts,
+    window_size: (f32, f32),
+) -> Frame {
+    let attempts = [true, true, false];
+    let mut frame = Frame {
+        slide_verts: Vec::new(),
+        verts: Vec::new(),
+    };
+    for (attempt, &with_image) in attempts.iter().enumerate() {
+        frame.slide_verts.clear();
+        frame.verts.clear();
+        let added = add_screen(
+            screen,
+            question.as_deref_mut(),
+            hud,
+            with_image,
+            textures,
+            fonts,
+            window_size,
+            &mut frame,
+        );
+        if added.is_ok() {
+            if !with_image && textures.slide_texture.is_none() {
+                textures.warn_atlas_full("the slide image is left out");
             }
+            return frame;
+        }
+        //What was added on the last try is still in the atlas, so it can be drawn.
+        if attempt + 1 < attempts.len() {
+            textures.atlas.clear();
         }
     }
 
-    header
+    textures.warn_atlas_full("some of it is left out");
+    frame
 }
 
-fn generate_question(
-    category: usize,
-    header: &Header,
-    slide: &Slide,
-    all_slides: &Vec<Slide>,
-) -> usize {
-    let mut rng = thread_rng();
+//The score so far, with how long the quiz has taken under it, right aligned in the bottom right corner.
+fn hud_text(score: Score, elapsed: Duration, fonts: &Fonts, window_size: (f32, f32)) -> TextRun {
+    let seconds = elapsed.as_secs();
+    let text = format!(
+        "Right: {}  Wrong: {}\n{}:{:02}",
+        score.number_right,
+        score.number_wrong,
+        seconds / 60,
+        seconds % 60
+    );
+    let layout = layout_text(fonts, HUD_TEXT_SIZE, &text, None, Alignment::Right);
+    let position = (
+        window_size.0 - layout.width as f32,
+        window_size.1 - layout.height as f32,
+    );
+    TextRun { layout, position }
+}
 
-    let mut answers: Vec<String> = Vec::new();
-    let right_answer_index = rng.gen_range(0, 3);
+fn question_widget(
+    question: &rusty_quiz::Question,
+    quiz: &Quiz,
+    icons: &gui_stuff::Icons,
+    fonts: &Fonts,
+) -> gui_stuff::Question {
+    match quiz.mode {
+        AnswerMode::MultipleChoice => gui_stuff::Question::new(question, icons, fonts),
+        AnswerMode::Typed => gui_stuff::Question::typed(question, icons, fonts),
+        AnswerMode::Reverse => {
+            let thumbnails = question
+                .choice_slides
+                .iter()
+                .map(|&i| slide_thumbnail(&quiz.header, &quiz.slides[i], fonts))
+                .collect();
+            gui_stuff::Question::with_images(question, thumbnails, icons, fonts)
+        }
+    }
+}
 
-    while answers.len() < 4 {
-        if answers.len() == right_answer_index {
-            answers.push(slide.answers[category].clone());
-        } else {
-            let answer = &rng
-                .choose(all_slides)
-                .expect("all_slides empty! Please fix!")
-                .answers[category];
-            if *answer != slide.answers[category] {
-                answers.push(answer.clone());
+//Reverse questions are about picking the slide, so it isn't shown. Cross questions put one of its answers in the prompt instead.
+fn shown_slide<'a>(quiz: &Quiz, slide: &'a Slide) -> Option<&'a Slide> {
+    match (quiz.mode, quiz.cross) {
+        (AnswerMode::Reverse, _) | (_, Some(_)) => None,
+        (AnswerMode::MultipleChoice, None) | (AnswerMode::Typed, None) => Some(slide),
+    }
+}
+
+//How many of the most missed slides the summary lists.
+const SUMMARY_MISSED_SLIDES: usize = 5;
+const SUMMARY_TEXT_SIZE: f32 = 22.0;
+
+fn score_text(score: Score) -> String {
+    format!(
+        "{} of {} ({:.0}%)",
+        score.number_right,
+        score.number_right + score.number_wrong,
+        score.percent_correct()
+    )
+}
+
+//The score for each category and then the slides missed most, one line each.
+fn breakdown_lines(session: &Session) -> Vec<String> {
+    let mut lines: Vec<String> = session
+        .category_scores()
+        .iter()
+        .map(|c| format!("{}: {}", c.category, score_text(c.score)))
+        .collect();
+    let missed = session.missed_slides();
+    if !missed.is_empty() {
+        lines.push("Most missed:".to_string());
+        lines.extend(
+            missed
+                .iter()
+                .take(SUMMARY_MISSED_SLIDES)
+                .map(|m| format!("{}: {} wrong", m.name, m.score.number_wrong)),
+        );
+    }
+    lines
+}
+
+//One line of text under the other, shown once the quiz is over.
+fn summary_screen<'a>(lines: &[String], fonts: &Fonts) -> Screen<'a> {
+    let mut texts = Vec::new();
+    let mut top = QUESTION_MARGIN;
+    for line in lines.iter() {
+        let layout = layout_text(fonts, SUMMARY_TEXT_SIZE, line, None, Alignment::Left);
+        let height = layout.height as f32;
+        texts.push(TextRun {
+            layout,
+            position: (QUESTION_MARGIN, top),
+        });
+        top += height;
+    }
+    Screen {
+        slide: None,
+        image: None,
+        texts,
+    }
+}
+
+//Missing characters still show up as boxes, but the user should know the fonts can't show all of the quiz.
+fn warn_missing_glyphs(quiz: &Quiz, quiz_file: &str, fonts: &Fonts) {
+    let warn = |place: String, texts: Vec<&String>| {
+        let mut missing: Vec<char> = Vec::new();
+        for text in texts {
+            for c in missing_glyphs(fonts, text) {
+                if !missing.contains(&c) {
+                    missing.push(c);
+                }
+            }
+        }
+        if !missing.is_empty() {
+            let characters: Vec<String> = missing.iter().map(|c| format!("'{}'", c)).collect();
+            eprintln!(
+                "{}: none of the fonts have glyphs for {}",
+                place,
+                characters.join(", ")
+            );
+        }
+    };
+
+    let header = &quiz.header;
+    warn(
+        quiz_file.to_string(),
+        header
+            .questions
+            .iter()
+            .chain(header.hint_labels.iter().flatten())
+            .chain(quiz.metadata.title.iter())
+            .collect(),
+    );
+    for slide in quiz.slides.iter() {
+        warn(
+            slide_location(Path::new(quiz_file), slide.line, 0).to_string(),
+            slide
+                .hints
+                .iter()
+                .chain(slide.answers.iter())
+                .chain(slide.aliases.iter().flatten())
+                .chain(slide.explanations.iter())
+                .collect(),
+        );
+    }
+}
+
+fn load_icon(name: &str) -> RgbaImage {
+    image::load_from_memory(&resources::load(name))
+        .expect("Unable to open icon!")
+        .to_rgba()
+}
+
+const USAGE: &str = "Usage: rusty_quiz [--choices N] [--typed [--tolerance N] | --reverse] [--given QUESTION --ask QUESTION] [--due] [--progress FILE] [--seed N] [--results FILE]
+                  [--font FILE]... [--bold-font FILE]... [--italic-font FILE]... [--mipmaps] <quiz file>
+       rusty_quiz check [--json] [--choices N] <quiz file>...
+       rusty_quiz convert <quiz file> <new .json or .toml file>";
+
+const CONTINUE_KEY: Key = Key::Enter;
+
+const CHOICE_KEYS: [Key; MAX_CHOICES] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+struct Options {
+    quiz_file: String,
+    //Overrides the number the deck asks for, if it asks for one.
+    choices: Option<usize>,
+    mode: AnswerMode,
+    //Typos allowed in every typed answer. Without it short answers allow fewer.
+    tolerance: Option<usize>,
+    //Question names for cross questions, looked up once the quiz is loaded.
+    cross: Option<(String, String)>,
+    //Only ask the cards the schedule says are due today.
+    due_only: bool,
+    progress_file: PathBuf,
+    //Replays a session, using the seed printed at the end of it.
+    seed: Option<u64>,
+    //A .csv or .json file to log each question to.
+    results_file: Option<PathBuf>,
+    //Tried in order for each character, before the built in font.
+    fonts: Vec<PathBuf>,
+    bold_fonts: Vec<PathBuf>,
+    italic_fonts: Vec<PathBuf>,
+    //Smoother slide images when they are shrunk a lot. The image gets a texture of its own for it.
+    mipmaps: bool,
+}
+
+fn parse_choices(value: Option<&String>) -> Result<usize, String> {
+    let value = value.ok_or("--choices needs a number")?;
+    match value.parse::<usize>() {
+        Ok(n) if (MIN_CHOICES..=MAX_CHOICES).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "--choices must be a number from {} to {}, not {}",
+            MIN_CHOICES, MAX_CHOICES, value
+        )),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut quiz_file = None;
+    let mut choices = None;
+    let mut mode = AnswerMode::MultipleChoice;
+    let mut tolerance = None;
+    let mut given = None;
+    let mut ask = None;
+    let mut due_only = false;
+    let mut progress_file = None;
+    let mut seed = None;
+    let mut results_file = None;
+    let mut fonts = Vec::new();
+    let mut bold_fonts = Vec::new();
+    let mut italic_fonts = Vec::new();
+    let mut mipmaps = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--choices" => choices = Some(parse_choices(args.next())?),
+            "--typed" => mode = AnswerMode::Typed,
+            "--reverse" => mode = AnswerMode::Reverse,
+            "--tolerance" => {
+                let value = args.next().ok_or("--tolerance needs a number")?;
+                tolerance = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("--tolerance must be a number, not {}", value))?,
+                );
+            }
+            "--given" => given = Some(args.next().ok_or("--given needs a question")?.clone()),
+            "--ask" => ask = Some(args.next().ok_or("--ask needs a question")?.clone()),
+            "--due" => due_only = true,
+            "--mipmaps" => mipmaps = true,
+            "--progress" => {
+                progress_file = Some(PathBuf::from(args.next().ok_or("--progress needs a file")?))
             }
+            "--results" => {
+                let file = PathBuf::from(args.next().ok_or("--results needs a file")?);
+                //Caught here rather than after the whole quiz has been taken.
+                if DeckFormat::from_path(&file) == DeckFormat::Toml {
+                    return Err("--results needs a .csv or .json file".to_string());
+                }
+                results_file = Some(file);
+            }
+            "--font" => fonts.push(PathBuf::from(args.next().ok_or("--font needs a file")?)),
+            "--bold-font" => bold_fonts.push(PathBuf::from(
+                args.next().ok_or("--bold-font needs a file")?,
+            )),
+            "--italic-font" => italic_fonts.push(PathBuf::from(
+                args.next().ok_or("--italic-font needs a file")?,
+            )),
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a number")?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("--seed must be a number, not {}", value))?,
+                );
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => quiz_file = Some(arg.clone()),
         }
     }
 
-    println!("{}?", header.questions[category]);
-    for i in 0..4 {
-        println!("[{}] {}", i + 1, answers[i]);
+    let quiz_file: String = quiz_file.ok_or("No quiz file given")?;
+    let cross = match (given, ask) {
+        (Some(given), Some(ask)) => Some((given, ask)),
+        (None, None) => None,
+        _ => return Err("--given and --ask go together".to_string()),
+    };
+    if cross.is_some() && mode == AnswerMode::Reverse {
+        return Err("--reverse can't be used with --given and --ask".to_string());
     }
+    //Progress is kept next to the quiz unless told otherwise.
+    let progress_file =
+        progress_file.unwrap_or_else(|| PathBuf::from(format!("{}.progress.json", quiz_file)));
+
+    Ok(Options {
+        quiz_file,
+        choices,
+        mode,
+        tolerance,
+        cross,
+        due_only,
+        progress_file,
+        seed,
+        results_file,
+        fonts,
+        bold_fonts,
+        italic_fonts,
+        mipmaps,
+    })
+}
+
+fn read_fonts(files: &[PathBuf]) -> Result<Vec<Font<'static>>, String> {
+    files
+        .iter()
+        .map(|file| {
+            let data = fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            Font::from_bytes(data).map_err(|e| format!("{}: {}", file.display(), e))
+        })
+        .collect()
+}
 
-    right_answer_index + 1
+//The built in font comes after the ones given, so there is always one to fall back on.
+fn load_fonts(options: &Options) -> Result<Fonts, String> {
+    let mut regular = read_fonts(&options.fonts)?;
+    regular
+        .push(Font::from_bytes(resources::load(resources::FONT)).expect("Error constructing Font"));
+    Ok(Fonts::new(
+        regular,
+        read_fonts(&options.bold_fonts)?,
+        read_fonts(&options.italic_fonts)?,
+    ))
 }
 
-fn generate_slide_texture(
-    slide: &Slide,
-    hint_image_sizes: &mut Vec<(f32, f32)>,
-    hint_offsets: &mut Vec<(f32, f32)>,
-    font: &Font,
-) -> RgbaImage {
-    let mut size: (f32, f32) = (0.0, 0.0);
-    let mut hint_images: Vec<RgbaImage> = Vec::new();
+fn cross_categories(header: &Header, given: &str, ask: &str) -> Result<CrossCategories, String> {
+    let category = |name: &str| {
+        header.category(name).ok_or_else(|| {
+            format!(
+                "There is no question called {}, the questions are: {}",
+                name,
+                header.questions.join(", ")
+            )
+        })
+    };
+    let categories = CrossCategories {
+        prompt: category(given)?,
+        target: category(ask)?,
+    };
+    if categories.prompt == categories.target {
+        return Err("--given and --ask need different questions".to_string());
+    }
+    Ok(categories)
+}
 
-    for h in slide.hints.iter() {
-        if h.is_empty() {
-            continue;
+fn run_check(args: &[String]) -> i32 {
+    let mut json = false;
+    let mut choices = None;
+    let mut files: Vec<&String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--choices" => match parse_choices(args.next()) {
+                Ok(n) => choices = Some(n),
+                Err(e) => {
+                    eprintln!("{}\n{}", e, USAGE);
+                    return 2;
+                }
+            },
+            _ => files.push(arg),
         }
-        let image = render_text(&font, 20.0, &h);
-        size.0 += image.width() as f32;
-        size.1 = size.1.max(image.height() as f32);
-        hint_image_sizes.push((image.width() as f32, image.height() as f32));
-        hint_images.push(image);
+    }
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
     }
 
-    let mut offset = (0, 0);
+    let reports: Vec<Report> = files
+        .iter()
+        .map(|f| check_quiz(Path::new(f), choices))
+        .collect();
 
-    if let Some(ref image) = slide.image {
-        size.0 += image.width() as f32;
-        size.1 = size.1.max(image.height() as f32);
-        offset.0 = image.width();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in reports.iter() {
+            for problem in report.problems.iter() {
+                println!("{}", problem);
+            }
+            if report.is_ok() {
+                println!("{}: ok", report.file.display());
+            }
+        }
     }
-    let mut texture = RgbaImage::new(size.0 as u32, size.1 as u32);
 
-    if let Some(ref image) = slide.image {
-        copy_image_into_image(image, (0, 0), &mut texture);
+    if reports.iter().all(|r| r.is_ok()) {
+        0
+    } else {
+        1
     }
-    for i in hint_images.iter() {
-        copy_image_into_image(&i, offset, &mut texture);
-        hint_offsets.push((offset.0 as f32, offset.1 as f32));
-        offset.1 += i.height();
+}
+
+fn run_convert(args: &[String]) -> i32 {
+    let (input, output) = match args {
+        [input, output] => (Path::new(input), Path::new(output)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let quiz = match Quiz::from_path_all_errors(input) {
+        Ok(quiz) => quiz,
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("{}", e);
+            }
+            return 1;
+        }
+    };
+    match write_quiz(&quiz, output) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {}", output.display(), e);
+            1
+        }
     }
-    texture
 }
 
 fn main() {
-    let font_data = std::fs::read("./resources/Ubuntu-R.ttf").expect("Unable to open font file!");
-    let font = Font::from_bytes(font_data.as_slice()).expect("Error constructing Font");
-
-    let mut csv_reader = {
-        let args: Vec<String> = env::args().collect();
-        println!("{:?}", args);
-        csv::Reader::from_path(
-            &args
-                .get(1)
-                .expect("No argument provided for quiz filename!"),
-        ).expect("Quiz file processing error!")
-    };
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("check") => std::process::exit(run_check(&args[2..])),
+        Some("convert") => std::process::exit(run_convert(&args[2..])),
+        _ => (),
+    }
 
-    let header = read_header(&mut csv_reader);
+    println!("{:?}", args);
+    let options = match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let slides = {
-        let mut slides = read_slides(&mut csv_reader, &header);
+    let fonts = match load_fonts(&options) {
+        Ok(fonts) => fonts,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-        thread_rng().shuffle(&mut slides);
+    let icons = gui_stuff::Icons {
+        right: load_icon(resources::RIGHT_ICON),
+        wrong: load_icon(resources::WRONG_ICON),
+        next: load_icon(resources::NEXT_ICON),
+    };
 
-        slides
+    let quiz = match Quiz::from_path_all_errors(&options.quiz_file) {
+        Ok(mut quiz) => {
+            if let Some(choices) = options.choices {
+                quiz.choices = choices;
+            }
+            quiz.mode = options.mode;
+            quiz.tolerance = options.tolerance;
+            if let Some((ref given, ref ask)) = options.cross {
+                match cross_categories(&quiz.header, given, ask) {
+                    Ok(categories) => quiz.cross = Some(categories),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(2);
+                    }
+                }
+            }
+            quiz
+        }
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("{}", e);
+            }
+            std::process::exit(1);
+        }
     };
 
-    let vert_src = {
-        let mut file = std::fs::File::open("./resources/vert.glsl").unwrap();
-        let mut src = String::new();
-        file.read_to_string(&mut src).unwrap();
-        src
+    warn_missing_glyphs(&quiz, &options.quiz_file, &fonts);
+
+    let mut schedule = match Schedule::load(&options.progress_file) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            eprintln!("{}: {}", options.progress_file.display(), e);
+            std::process::exit(1);
+        }
     };
 
-    let frag_src = {
-        let mut file = std::fs::File::open("./resources/frag.glsl").unwrap();
-        let mut src = String::new();
-        file.read_to_string(&mut src).unwrap();
-        src
+    let today = today();
+    let keep =
+        |slide, category| !options.due_only || schedule.is_due(&quiz, slide, category, today);
+    let mut session = match options.seed {
+        Some(seed) => Session::seeded(&quiz, seed, keep),
+        None => Session::filtered(&quiz, keep),
     };
+    if session.is_finished() {
+        println!("Nothing is due today.");
+        return;
+    }
+
+    let vert_src = resources::load_string(resources::VERTEX_SHADER);
+    let frag_src = resources::load_string(resources::FRAGMENT_SHADER);
 
     let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
     glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
@@ -512,10 +1382,19 @@ fn main() {
         glfw::OpenGlProfileHint::Core,
     ));
 
-    let mut window = glfw
-        .create_window(1000, 500, "Rusty Quiz", glfw::WindowMode::Windowed)
-        .unwrap()
-        .0;
+    let (mut window, events) = glfw
+        .create_window(
+            1000,
+            500,
+            quiz.metadata.title.as_deref().unwrap_or("Rusty Quiz"),
+            glfw::WindowMode::Windowed,
+        )
+        .unwrap();
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_char_polling(true);
+    window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
 
     gl::load_with(|s| window.get_proc_address(s) as *const std::os::raw::c_void);
 
@@ -526,166 +1405,273 @@ fn main() {
         opengl_setup();
         setup_shaders(&vert_src, &frag_src);
         vertex_buffer_setup(&mut vert_buffer);
-        texture_setup(&mut texture_buffer);
     }
 
+    let slide_texture = if options.mipmaps {
+        let mut texture: GLuint = 0;
+        unsafe {
+            texture_setup(&mut texture, true);
+        }
+        Some(SlideTexture {
+            texture,
+            slide: None,
+            size: (0.0, 0.0),
+        })
+    } else {
+        None
+    };
+    let mut textures = unsafe {
+        //Bound last, so the atlas is what gets drawn from and uploaded to.
+        texture_setup(&mut texture_buffer, false);
+        let mut atlas = TextureAtlas::new(max_texture_size().min(MAX_ATLAS_SIZE));
+        atlas.create_texture();
+        Textures::new(atlas, texture_buffer, slide_texture)
+    };
+
     print_gl_error();
 
-    let mut texture: RgbaImage = RgbaImage::new(0, 0);
-    let mut hint_offsets: Vec<(f32, f32)> = Vec::new();
-    let mut hint_image_sizes: Vec<(f32, f32)> = Vec::new();
+    let mut screen = Screen {
+        slide: None,
+        image: None,
+        texts: Vec::new(),
+    };
+    //The window size the screen was laid out for.
+    let mut screen_size = window_size(&window);
+    let mut question: Option<gui_stuff::Question> = None;
+    let started = Instant::now();
 
-    let mut slides_iter = slides.iter();
-    let mut current_slide = slides_iter.next();
-    let mut current_category = 0;
-    let mut right_answer = 0;
-    let mut next_question = false;
+    let mut mouse_pos: (f32, f32) = (0.0, 0.0);
+    let mut mouse_down = false;
 
-    let mut already_guessed = false;
-    let mut number_right = 0;
-    let mut number_wrong = 0;
+    let mut next_question = false;
+    //The session starts timing a question when it is made, which for the first one is before the window is even open.
+    let mut question_shown = false;
+
+    //Typed since the last frame, for typed questions.
+    let mut typed_chars: Vec<char> = Vec::new();
+    let mut backspaces = 0;
+
+    //The number keys pick a choice and the last key moves on to the next question.
+    //Typed questions need the number keys for typing, so they only get the last one.
+    let choice_keys = match quiz.mode {
+        AnswerMode::MultipleChoice | AnswerMode::Reverse => quiz.choices,
+        AnswerMode::Typed => 0,
+    };
+    let mut keys: Vec<Key> = CHOICE_KEYS[..choice_keys].to_vec();
+    keys.push(CONTINUE_KEY);
+    let mut old_key_state: Vec<bool> = vec![false; keys.len()];
+    let mut key_activated: Vec<bool> = vec![false; keys.len()];
+
+    if let Some(slide) = session.current_slide() {
+        let mut widget =
+            question_widget(session.current_question().unwrap(), &quiz, &icons, &fonts);
+        screen = question_screen(
+            &quiz.header,
+            shown_slide(&quiz, slide),
+            &mut widget,
+            &fonts,
+            screen_size,
+        );
+        question = Some(widget);
+    }
 
-    let mut old_key_state: [bool; 4] = [false; 4];
-    let mut key_activated: [bool; 4] = [false; 4];
+    while !window.should_close() && !session.is_finished() {
+        let window_size = window_size(&window);
+        let widget = question.as_mut().unwrap();
+        if window_size != screen_size {
+            screen = question_screen(&quiz.header, screen.slide, widget, &fonts, window_size);
+            screen_size = window_size;
+        }
+        widget.update_state(mouse_pos, mouse_down);
+        let mut choice = widget.clicked_answer();
 
-    if let Some(slide) = current_slide {
-        right_answer = generate_question(current_category, &header, &slide, &slides);
-        texture = generate_slide_texture(&slide, &mut hint_image_sizes, &mut hint_offsets, &font);
-        unsafe {
-            set_texture_data(&texture);
-        }
-    }
-
-    while !window.should_close() && current_slide.is_some() {
-        if let Some(slide) = current_slide {
-            let window_size = window.get_size();
-            let window_size = (window_size.0 as f32, window_size.1 as f32);
-            for i in 0..4 {
-                if key_activated[i] {
-                    if i + 1 == right_answer {
-                        println!("Answer #{} is correct", right_answer);
-                        if !already_guessed {
-                            number_right += 1;
-                        }
-                        next_question = true;
-                    } else {
-                        println!("Try again!");
-                        if !already_guessed {
-                            number_wrong += 1;
-                            already_guessed = true;
-                        }
-                    }
-                }
+        let mut texture_changed = false;
+        for c in typed_chars.drain(..) {
+            texture_changed |= widget.type_char(c, &fonts);
+        }
+        for _ in 0..backspaces {
+            texture_changed |= widget.backspace(&fonts);
+        }
+        backspaces = 0;
+
+        let number_of_choices = session.current_question().unwrap().choices.len();
+        for (i, activated) in key_activated
+            .iter()
+            .take(number_of_choices.min(choice_keys))
+            .enumerate()
+        {
+            if *activated {
+                choice = Some(i);
             }
+        }
 
-            let mut verts: Vec<GLfloat> = Vec::new();
-
-            if let Some(ref image) = slide.image {
-                add_textured_box(
-                    (0.0, 0.0),
-                    0.0,
-                    (0.0, 0.0),
-                    (1.0, 1.0, 1.0),
-                    (image.width() as f32, image.height() as f32),
-                    window_size,
-                    (texture.width() as f32, texture.height() as f32),
-                    &mut verts,
-                );
+        if let Some(i) = choice {
+            if !widget.is_answered() {
+                widget.choose(i);
+                session.answer(i);
             }
+        }
 
-            for i in 0..hint_offsets.len() {
-                add_textured_box(
-                    hint_offsets[i],
-                    0.0,
-                    hint_offsets[i],
-                    (0.1, 0.1, 0.1),
-                    hint_image_sizes[i],
-                    window_size,
-                    (texture.width() as f32, texture.height() as f32),
-                    &mut verts,
-                );
+        //The continue key also hands in a typed answer, as long as something was typed.
+        let typed_answer = widget.typed_text().map(|t| t.trim().to_string());
+        match typed_answer {
+            Some(ref text) if !widget.is_answered() => {
+                if key_activated[choice_keys] && !text.is_empty() {
+                    let guess = session.answer_text(text);
+                    widget.submit(guess == Guess::Right, &fonts);
+                    texture_changed = true;
+                }
             }
-
-            unsafe {
-                set_vertex_data(&verts);
-                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-                let mut number_of_tris = hint_offsets.len() * 6;
-                if slide.image.is_some() {
-                    number_of_tris += 6;
+            _ => {
+                if widget.is_answered() && (widget.continue_clicked() || key_activated[choice_keys])
+                {
+                    next_question = true;
                 }
-                gl::DrawArrays(gl::TRIANGLES, 0, number_of_tris as i32);
             }
-            window.swap_buffers();
-
-            if next_question {
-                next_question = false;
-                already_guessed = false;
-                current_category += 1;
-                if current_category < header.questions.len() {
-                    right_answer = generate_question(current_category, &header, &slide, &slides);
-                } else {
-                    current_slide = slides_iter.next();
-                    current_category = 0;
-                    if let Some(slide) = current_slide {
-                        right_answer =
-                            generate_question(current_category, &header, &slide, &slides);
-                        texture = generate_slide_texture(
-                            &slide,
-                            &mut hint_image_sizes,
-                            &mut hint_offsets,
-                            &font,
-                        );
-                        unsafe {
-                            set_texture_data(&texture);
-                        }
-                    }
+        }
+
+        if texture_changed {
+            textures.redraw_question(widget);
+        }
+
+        let hud = hud_text(session.score(), started.elapsed(), &fonts, window_size);
+        let frame = screen_verts(
+            &screen,
+            Some(widget),
+            Some(&hud),
+            &mut textures,
+            &fonts,
+            window_size,
+        );
+        unsafe {
+            draw_frame(&frame, &mut textures);
+        }
+        window.swap_buffers();
+        if !question_shown {
+            session.start_timer();
+            question_shown = true;
+        }
+
+        if next_question {
+            next_question = false;
+            match session.advance() {
+                Progress::NextQuestion | Progress::NextSlide => {
+                    let mut widget =
+                        question_widget(session.current_question().unwrap(), &quiz, &icons, &fonts);
+                    screen = question_screen(
+                        &quiz.header,
+                        shown_slide(&quiz, session.current_slide().unwrap()),
+                        &mut widget,
+                        &fonts,
+                        screen_size,
+                    );
+                    question = Some(widget);
+                    question_shown = false;
+                    textures.new_question();
                 }
+                Progress::Finished => (),
             }
         }
         glfw.poll_events();
+        for (_, event) in glfw::flush_messages(&events) {
+            match event {
+                WindowEvent::CursorPos(x, y) => mouse_pos = (x as f32, y as f32),
+                WindowEvent::MouseButton(MouseButton::Button1, action, _) => {
+                    mouse_down = action != Action::Release;
+                }
+                WindowEvent::Char(c) => typed_chars.push(c),
+                WindowEvent::Key(Key::Backspace, _, Action::Press, _)
+                | WindowEvent::Key(Key::Backspace, _, Action::Repeat, _) => backspaces += 1,
+                WindowEvent::FramebufferSize(width, height) => unsafe {
+                    gl::Viewport(0, 0, width, height);
+                },
+                _ => {}
+            }
+        }
 
         for x in key_activated.iter_mut() {
             *x = false;
         }
 
-        if window.get_key(Key::Num1) == Action::Release {
-            if old_key_state[0] {
-                key_activated[0] = true;
+        for (i, key) in keys.iter().enumerate() {
+            if window.get_key(*key) == Action::Release {
+                if old_key_state[i] {
+                    key_activated[i] = true;
+                }
+                old_key_state[i] = false;
+            } else {
+                old_key_state[i] = true;
             }
-            old_key_state[0] = false;
-        } else {
-            old_key_state[0] = true;
         }
-        if window.get_key(Key::Num2) == Action::Release {
-            if old_key_state[1] {
-                key_activated[1] = true;
-            }
-            old_key_state[1] = false;
-        } else {
-            old_key_state[1] = true;
+    }
+    for review in session.reviews().iter() {
+        schedule.review(&quiz, review, today);
+    }
+    if let Err(e) = schedule.save(&options.progress_file) {
+        eprintln!(
+            "Unable to save progress to {}: {}",
+            options.progress_file.display(),
+            e
+        );
+    }
+
+    if let Some(ref results_file) = options.results_file {
+        let results = SessionResults::new(&options.quiz_file, &session, today);
+        if let Err(e) = write_results(&results, results_file) {
+            eprintln!(
+                "Unable to save results to {}: {}",
+                results_file.display(),
+                e
+            );
         }
-        if window.get_key(Key::Num3) == Action::Release {
-            if old_key_state[2] {
-                key_activated[2] = true;
-            }
-            old_key_state[2] = false;
-        } else {
-            old_key_state[2] = true;
+    }
+
+    let score = session.score();
+    println!("You're done!");
+    println!("Number right: {}", score.number_right);
+    println!("Number wrong: {}", score.number_wrong);
+    println!("Percent correct: {}%", score.percent_correct());
+    for line in breakdown_lines(&session) {
+        println!("{}", line);
+    }
+    println!("Seed: {}", session.seed());
+
+    //The summary stays up until the window is closed or the continue key is pressed.
+    if !session.is_finished() {
+        return;
+    }
+    let mut lines = vec![
+        "You're done!".to_string(),
+        format!("Right: {}", score_text(score)),
+    ];
+    lines.extend(breakdown_lines(&session));
+    lines.push(format!("Seed: {}", session.seed()));
+    let screen = summary_screen(&lines, &fonts);
+
+    let mut closing = false;
+    while !window.should_close() && !closing {
+        let frame = screen_verts(
+            &screen,
+            None,
+            None,
+            &mut textures,
+            &fonts,
+            window_size(&window),
+        );
+        unsafe {
+            draw_frame(&frame, &mut textures);
         }
-        if window.get_key(Key::Num4) == Action::Release {
-            if old_key_state[3] {
-                key_activated[3] = true;
+        window.swap_buffers();
+
+        glfw.wait_events();
+        for (_, event) in glfw::flush_messages(&events) {
+            match event {
+                WindowEvent::Key(CONTINUE_KEY, _, Action::Press, _) => closing = true,
+                WindowEvent::FramebufferSize(width, height) => unsafe {
+                    gl::Viewport(0, 0, width, height);
+                },
+                _ => {}
             }
-            old_key_state[3] = false;
-        } else {
-            old_key_state[3] = true;
         }
     }
-    println!("You're done!");
-    println!("Number right: {}", number_right);
-    println!("Number wrong: {}", number_wrong);
-    println!(
-        "Percent correct: {}%",
-        (number_right as f32 / (number_right + number_wrong) as f32) * 100.0
-    );
 }
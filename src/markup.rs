@@ -0,0 +1,123 @@
+//Emphasis markup for shown text. It is kept out of the window code so it can be tested without a GUI.
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+pub type StyledChar = (char, Style);
+
+//**bold** and *italic*, with \* for an asterisk and \\ for a backslash.
+//A marker only starts emphasis if it is right in front of some text and closed later on, so "2 * 3" is left alone.
+pub fn parse_emphasis(text: &str) -> Vec<StyledChar> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Token {
+        Char(char),
+        Marker(usize),
+    }
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&next) if next == '*' || next == '\\' => {
+                    chars.next();
+                    tokens.push(Token::Char(next));
+                }
+                _ => tokens.push(Token::Char(c)),
+            },
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                tokens.push(Token::Marker(2));
+            }
+            '*' => tokens.push(Token::Marker(1)),
+            c => tokens.push(Token::Char(c)),
+        }
+    }
+
+    let mut styled = Vec::new();
+    let mut style = Style::default();
+    for (i, &token) in tokens.iter().enumerate() {
+        let marker = match token {
+            Token::Char(c) => {
+                styled.push((c, style));
+                continue;
+            }
+            Token::Marker(marker) => marker,
+        };
+        let open = if marker == 2 {
+            style.bold
+        } else {
+            style.italic
+        };
+        let opens = match tokens.get(i + 1) {
+            Some(&Token::Char(c)) => !c.is_whitespace(),
+            Some(&Token::Marker(_)) => true,
+            None => false,
+        } && tokens[i + 1..].contains(&token);
+
+        if open || opens {
+            if marker == 2 {
+                style.bold = !style.bold;
+            } else {
+                style.italic = !style.italic;
+            }
+        } else {
+            styled.extend((0..marker).map(|_| ('*', style)));
+        }
+    }
+    styled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Each run of characters with the same style.
+    fn runs(text: &str) -> Vec<(String, Style)> {
+        let mut runs: Vec<(String, Style)> = Vec::new();
+        for (c, style) in parse_emphasis(text) {
+            match runs.last_mut() {
+                Some(run) if run.1 == style => run.0.push(c),
+                _ => runs.push((c.to_string(), style)),
+            }
+        }
+        runs
+    }
+
+    fn plain(text: &str) -> (String, Style) {
+        (text.to_string(), Style::default())
+    }
+
+    fn styled(text: &str, bold: bool, italic: bool) -> (String, Style) {
+        (text.to_string(), Style { bold, italic })
+    }
+
+    #[test]
+    fn markers_switch_style() {
+        assert_eq!(
+            runs("a **bold** and *italic* word"),
+            vec![
+                plain("a "),
+                styled("bold", true, false),
+                plain(" and "),
+                styled("italic", false, true),
+                plain(" word"),
+            ]
+        );
+        assert_eq!(
+            runs("***both*** after"),
+            vec![styled("both", true, true), plain(" after")]
+        );
+    }
+
+    #[test]
+    fn unmatched_markers_and_escapes_are_text() {
+        assert_eq!(runs("2 * 3 = 6"), vec![plain("2 * 3 = 6")]);
+        assert_eq!(runs("*open"), vec![plain("*open")]);
+        assert_eq!(runs(r"\*not\* \\"), vec![plain(r"*not* \")]);
+        assert_eq!(runs("**"), vec![plain("**")]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::mem;
+
+//How many typos a typed answer may have and still count as right, unless a tolerance is given.
+pub const DEFAULT_TOLERANCE: usize = 1;
+//Short answers are easy to mistype into a different answer, so without a given tolerance each typo needs this many characters.
+const CHARACTERS_PER_TYPO: usize = 4;
+
+//Lowercases, drops accents and turns each run of whitespace and punctuation into one space.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::new();
+    for c in text.chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            match fold_diacritic(c) {
+                Some(plain) => normalized.push_str(plain),
+                None => normalized.push(c),
+            }
+        } else if !normalized.is_empty() && !normalized.ends_with(' ') {
+            normalized.push(' ');
+        }
+    }
+    if normalized.ends_with(' ') {
+        normalized.pop();
+    }
+    normalized
+}
+
+//Only covers the Latin letters, which is what the decks so far use.
+fn fold_diacritic(c: char) -> Option<&'static str> {
+    let plain = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(plain)
+}
+
+//Levenshtein distance, counted in characters rather than bytes.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+//True if the typed text is close enough to any of the accepted answers.
+//A given tolerance applies to every answer. Without one, short answers allow fewer typos than DEFAULT_TOLERANCE.
+pub fn matches_answer(typed: &str, accepted: &[String], tolerance: Option<usize>) -> bool {
+    let typed = normalize(typed);
+    if typed.is_empty() {
+        return false;
+    }
+
+    accepted.iter().any(|answer| {
+        let answer = normalize(answer);
+        let allowed = tolerance
+            .unwrap_or_else(|| DEFAULT_TOLERANCE.min(answer.chars().count() / CHARACTERS_PER_TYPO));
+        edit_distance(&typed, &answer) <= allowed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(accepted: &[&str]) -> Vec<String> {
+        accepted.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_folds_case_accents_and_punctuation() {
+        assert_eq!(normalize("  Côte d'Ivoire! "), "cote d ivoire");
+        assert_eq!(normalize("Straße"), "strasse");
+        assert_eq!(normalize("New   York--City"), "new york city");
+        assert_eq!(normalize("?!"), "");
+    }
+
+    #[test]
+    fn edit_distance_counts_characters() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        assert_eq!(edit_distance("é", "e"), 1);
+    }
+
+    #[test]
+    fn matches_answer_allows_typos_by_length() {
+        let accepted = answers(&["Paris", "Lutetia"]);
+        assert!(matches_answer("paris", &accepted, None));
+        assert!(matches_answer("Pariss", &accepted, None));
+        assert!(matches_answer("lutetia", &accepted, None));
+        assert!(!matches_answer("Parsi", &accepted, None));
+        assert!(!matches_answer("", &accepted, None));
+
+        //Too short for a typo unless a tolerance is given.
+        let short = answers(&["Yen"]);
+        assert!(!matches_answer("Yan", &short, None));
+        assert!(matches_answer("Yan", &short, Some(1)));
+        assert!(!matches_answer("Pariss", &accepted, Some(0)));
+    }
+}
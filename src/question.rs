@@ -0,0 +1,365 @@
+use deck::{Header, Slide};
+use matching::{matches_answer, normalize};
+use rand::{seq, Rng};
+use std::collections::HashSet;
+
+pub const DEFAULT_CHOICES: usize = 4;
+//Choices are picked with the number keys, so there can't be more than nine.
+pub const MIN_CHOICES: usize = 2;
+pub const MAX_CHOICES: usize = 9;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnswerMode {
+    //Pick the answer from a list built out of other slides' answers.
+    MultipleChoice,
+    //Type the answer in.
+    Typed,
+    //Shown an answer, pick which slide it belongs to.
+    Reverse,
+}
+
+//Ask for one answer of a slide given another, like the currency of a country, instead of showing the slide.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CrossCategories {
+    pub prompt: usize,
+    pub target: usize,
+}
+
+pub struct Question {
+    pub category: usize,
+    pub prompt: String,
+    pub choices: Vec<String>,
+    //Index into choices, starting at 0.
+    pub right_answer: usize,
+    //Everything a typed answer is compared against.
+    pub accepted: Vec<String>,
+    //Shown once the question is answered.
+    pub explanation: Option<String>,
+    //For reverse questions, the slide each choice stands for. Empty otherwise.
+    pub choice_slides: Vec<usize>,
+}
+
+impl Question {
+    pub fn is_right(&self, choice: usize) -> bool {
+        choice == self.right_answer
+    }
+
+    pub fn is_right_text(&self, typed: &str, tolerance: Option<usize>) -> bool {
+        matches_answer(typed, &self.accepted, tolerance)
+    }
+}
+
+//One canonical answer for each different answer in a category, leaving out any that share a name with `excluded`.
+//Answers are compared with their aliases after normalizing, so "USA|United States", "United States" and "usa" count once.
+//check_quiz counts these too, so it warns about exactly the decks that get fewer choices.
+pub fn distinct_answers<'a>(
+    category: usize,
+    excluded: &[String],
+    all_slides: &'a [Slide],
+) -> Vec<&'a str> {
+    let mut seen: HashSet<String> = excluded.iter().map(|a| normalize(a)).collect();
+    let mut pool: Vec<&'a str> = Vec::new();
+    for slide in all_slides.iter() {
+        let answer = match slide.answers.get(category) {
+            Some(answer) if !answer.is_empty() => answer,
+            _ => continue,
+        };
+        let names: Vec<String> = slide
+            .accepted_answers(category)
+            .iter()
+            .map(|a| normalize(a))
+            .collect();
+        if names.iter().all(|n| !seen.contains(n)) {
+            seen.extend(names);
+            pool.push(answer);
+        }
+    }
+    pool
+}
+
+//Picks up to `amount` different wrong answers for a category from distinct_answers, never repeating one.
+//A synonym of the right answer or of another distractor is never picked. Small decks just get fewer of them.
+pub fn sample_distractors<R: Rng>(
+    rng: &mut R,
+    category: usize,
+    right_answers: &[String],
+    all_slides: &[Slide],
+    amount: usize,
+) -> Vec<String> {
+    let pool = distinct_answers(category, right_answers, all_slides);
+    let amount = amount.min(pool.len());
+    seq::sample_slice(rng, &pool, amount)
+        .into_iter()
+        .map(|a| a.to_string())
+        .collect()
+}
+
+pub fn generate_question<R: Rng>(
+    rng: &mut R,
+    category: usize,
+    header: &Header,
+    slide: &Slide,
+    all_slides: &[Slide],
+    choices: usize,
+) -> Question {
+    let prompt = format!("{}?", header.questions[category]);
+    let accepted = slide.accepted_answers(category);
+    build_question(rng, category, prompt, slide, accepted, all_slides, choices)
+}
+
+//The prompt is the slide's answer for one category and the choices come from another, the same way generate_question picks them.
+//Every slide with the same prompt answer is a right answer too, like each country using the Euro, so none of them are offered as wrong ones.
+pub fn generate_cross_question<R: Rng>(
+    rng: &mut R,
+    categories: CrossCategories,
+    header: &Header,
+    slide: &Slide,
+    all_slides: &[Slide],
+    choices: usize,
+) -> Question {
+    let prompt = format!(
+        "{} of {}?",
+        header.questions[categories.target], slide.answers[categories.prompt]
+    );
+
+    let prompt_answer = normalize(&slide.answers[categories.prompt]);
+    let mut accepted = slide.accepted_answers(categories.target);
+    for other in all_slides.iter() {
+        let shares_prompt = other
+            .answers
+            .get(categories.prompt)
+            .is_some_and(|a| normalize(a) == prompt_answer);
+        if !shares_prompt {
+            continue;
+        }
+        for answer in other.accepted_answers(categories.target) {
+            if !answer.is_empty() && !accepted.contains(&answer) {
+                accepted.push(answer);
+            }
+        }
+    }
+    build_question(
+        rng,
+        categories.target,
+        prompt,
+        slide,
+        accepted,
+        all_slides,
+        choices,
+    )
+}
+
+fn build_question<R: Rng>(
+    rng: &mut R,
+    category: usize,
+    prompt: String,
+    slide: &Slide,
+    accepted: Vec<String>,
+    all_slides: &[Slide],
+    choices: usize,
+) -> Question {
+    let right_answer = &slide.answers[category];
+    let mut answers = sample_distractors(rng, category, &accepted, all_slides, choices - 1);
+    let right_answer_index = rng.gen_range(0, answers.len() + 1);
+    answers.insert(right_answer_index, right_answer.clone());
+
+    Question {
+        category,
+        prompt,
+        choices: answers,
+        right_answer: right_answer_index,
+        accepted,
+        explanation: explanation(slide, category),
+        choice_slides: Vec::new(),
+    }
+}
+
+fn explanation(slide: &Slide, category: usize) -> Option<String> {
+    slide
+        .explanations
+        .get(category)
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .map(|e| e.to_string())
+}
+
+//What a slide is called when it has to be picked by name rather than shown.
+pub fn slide_label(header: &Header, slide: &Slide) -> String {
+    if let Some(hint) = header.hint_text(slide).into_iter().next() {
+        return hint;
+    }
+    match slide.image_path {
+        Some(ref path) => path.display().to_string(),
+        None => format!("Line {}", slide.line),
+    }
+}
+
+//Shows one of the slide's answers and asks which slide it belongs to.
+//The other choices are slides with a different answer for the category, so only one choice can be right.
+pub fn generate_reverse_question<R: Rng>(
+    rng: &mut R,
+    category: usize,
+    header: &Header,
+    slide: usize,
+    all_slides: &[Slide],
+    choices: usize,
+) -> Question {
+    let right_slide = &all_slides[slide];
+    let accepted = right_slide.accepted_answers(category);
+    let names: HashSet<String> = accepted.iter().map(|a| normalize(a)).collect();
+    let pool: Vec<usize> = all_slides
+        .iter()
+        .enumerate()
+        .filter(|&(_, s)| match s.answers.get(category) {
+            Some(answer) if !answer.is_empty() => s
+                .accepted_answers(category)
+                .iter()
+                .all(|a| !names.contains(&normalize(a))),
+            _ => false,
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    let amount = (choices - 1).min(pool.len());
+    let mut choice_slides: Vec<usize> = seq::sample_slice(rng, &pool, amount);
+    let right_answer_index = rng.gen_range(0, choice_slides.len() + 1);
+    choice_slides.insert(right_answer_index, slide);
+
+    Question {
+        category,
+        prompt: format!(
+            "Whose {} is {}?",
+            header.questions[category], right_slide.answers[category]
+        ),
+        choices: choice_slides
+            .iter()
+            .map(|&i| slide_label(header, &all_slides[i]))
+            .collect(),
+        right_answer: right_answer_index,
+        accepted: vec![slide_label(header, right_slide)],
+        explanation: explanation(right_slide, category),
+        choice_slides,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prng::ChaChaRng;
+    use rand::SeedableRng;
+
+    fn slide(line: u64, answers: &[&str], aliases: &[&[&str]]) -> Slide {
+        Slide {
+            line,
+            image_path: None,
+            image: None,
+            hints: vec![format!("Slide {}", line)],
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+            aliases: aliases
+                .iter()
+                .map(|a| a.iter().map(|a| a.to_string()).collect())
+                .collect(),
+            explanations: Vec::new(),
+        }
+    }
+
+    fn rng(seed: u8) -> ChaChaRng {
+        ChaChaRng::from_seed([seed; 32])
+    }
+
+    fn currencies() -> Vec<Slide> {
+        vec![
+            slide(2, &["France", "Euro"], &[&[], &[]]),
+            slide(3, &["Germany", "Euro"], &[&[], &[]]),
+            slide(4, &["United States", "Dollar"], &[&["USA"], &["USD"]]),
+            slide(5, &["Ecuador", "US Dollar"], &[&[], &["Dollar"]]),
+            slide(6, &["Japan", "Yen"], &[&[], &[]]),
+            slide(7, &["United Kingdom", "Pound"], &[&["UK"], &["Sterling"]]),
+            slide(8, &["Switzerland", "Franc"], &[&[], &[]]),
+        ]
+    }
+
+    #[test]
+    fn distinct_answers_count_aliases_once() {
+        let slides = currencies();
+        assert_eq!(
+            distinct_answers(1, &[], &slides),
+            vec!["Euro", "Dollar", "Yen", "Pound", "Franc"]
+        );
+        let excluded = vec!["usd".to_string()];
+        assert_eq!(
+            distinct_answers(1, &excluded, &slides),
+            vec!["Euro", "US Dollar", "Yen", "Pound", "Franc"]
+        );
+    }
+
+    #[test]
+    fn distractors_are_distinct_and_never_right() {
+        let slides = currencies();
+        let right = slides[2].accepted_answers(1);
+        for seed in 0..50 {
+            let distractors = sample_distractors(&mut rng(seed), 1, &right, &slides, 3);
+            assert_eq!(distractors.len(), 3);
+            let names: HashSet<String> = distractors.iter().map(|d| normalize(d)).collect();
+            assert_eq!(names.len(), 3);
+            assert!(!names.contains("dollar") && !names.contains("us dollar"));
+        }
+    }
+
+    #[test]
+    fn small_pools_give_fewer_distractors() {
+        let slides = vec![
+            slide(2, &["Yes"], &[&[]]),
+            slide(3, &["No"], &[&[]]),
+            slide(4, &["yes"], &[&[]]),
+        ];
+        let right = vec!["Yes".to_string()];
+        assert_eq!(
+            sample_distractors(&mut rng(0), 0, &right, &slides, 3),
+            vec!["No"]
+        );
+        assert!(sample_distractors(&mut rng(0), 0, &right, &slides[..1], 3).is_empty());
+    }
+
+    #[test]
+    fn right_answer_can_be_any_choice() {
+        let slides = currencies();
+        let header = Header::new(
+            vec![None],
+            vec!["Country".to_string(), "Currency".to_string()],
+        );
+        let mut seen = HashSet::new();
+        for seed in 0..50 {
+            let question = generate_question(&mut rng(seed), 1, &header, &slides[5], &slides, 4);
+            assert_eq!(question.choices.len(), 4);
+            assert_eq!(question.choices[question.right_answer], "Pound");
+            seen.insert(question.right_answer);
+        }
+        assert_eq!(seen.len(), 4);
+    }
+
+    #[test]
+    fn cross_questions_accept_every_slide_with_the_prompt() {
+        let slides = currencies();
+        let header = Header::new(
+            vec![None],
+            vec!["Country".to_string(), "Currency".to_string()],
+        );
+        let categories = CrossCategories {
+            prompt: 1,
+            target: 0,
+        };
+        for seed in 0..20 {
+            let question = generate_cross_question(
+                &mut rng(seed),
+                categories,
+                &header,
+                &slides[0],
+                &slides,
+                4,
+            );
+            assert!(question.is_right_text("Germany", None));
+            assert!(!question.choices.contains(&"Germany".to_string()));
+        }
+    }
+}
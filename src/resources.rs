@@ -0,0 +1,63 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub const FONT: &str = "Ubuntu-R.ttf";
+pub const VERTEX_SHADER: &str = "vert.glsl";
+pub const FRAGMENT_SHADER: &str = "frag.glsl";
+pub const RIGHT_ICON: &str = "icons8-checked-50.png";
+pub const WRONG_ICON: &str = "icons8-cancel-50.png";
+pub const NEXT_ICON: &str = "icons8-circled-right-50.png";
+
+//Built into the binary so it runs from any directory.
+const EMBEDDED: [(&str, &[u8]); 6] = [
+    (FONT, include_bytes!("../resources/Ubuntu-R.ttf")),
+    (VERTEX_SHADER, include_bytes!("../resources/vert.glsl")),
+    (FRAGMENT_SHADER, include_bytes!("../resources/frag.glsl")),
+    (
+        RIGHT_ICON,
+        include_bytes!("../resources/icons8-checked-50.png"),
+    ),
+    (
+        WRONG_ICON,
+        include_bytes!("../resources/icons8-cancel-50.png"),
+    ),
+    (
+        NEXT_ICON,
+        include_bytes!("../resources/icons8-circled-right-50.png"),
+    ),
+];
+
+//Directories listed in RUSTY_QUIZ_RESOURCES come first, then the resources directory next to the executable and then the one in the working directory.
+fn search_path() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match env::var_os("RUSTY_QUIZ_RESOURCES") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => Vec::new(),
+    };
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))
+    {
+        dirs.push(exe_dir.join("resources"));
+    }
+    dirs.push(PathBuf::from("resources"));
+    dirs
+}
+
+//The first file called `name` on the search path, or the built in copy if there isn't one.
+pub fn load(name: &str) -> Vec<u8> {
+    for dir in search_path() {
+        if let Ok(data) = fs::read(dir.join(name)) {
+            return data;
+        }
+    }
+    EMBEDDED
+        .iter()
+        .find(|&&(embedded_name, _)| embedded_name == name)
+        .map(|&(_, data)| data.to_vec())
+        .expect("Unknown resource!")
+}
+
+pub fn load_string(name: &str) -> String {
+    String::from_utf8(load(name)).expect("Resource is not valid UTF-8!")
+}
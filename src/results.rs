@@ -0,0 +1,235 @@
+use deck::ALIAS_SEPARATOR;
+use format::DeckFormat;
+use session::{CategoryScore, MissedSlide, QuestionResult, Score, Session};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Everything that goes in a results file. The date and seed tell runs apart, and the seed can replay one.
+#[derive(Debug, Serialize)]
+pub struct SessionResults {
+    pub quiz: String,
+    pub date: String,
+    pub seed: u64,
+    pub number_right: u32,
+    pub number_wrong: u32,
+    pub categories: Vec<CategoryScore>,
+    pub missed_slides: Vec<MissedSlide>,
+    pub questions: Vec<QuestionResult>,
+}
+
+impl SessionResults {
+    //`today` is in days since the Unix epoch, as returned by today().
+    pub fn new(quiz_file: &str, session: &Session, today: u64) -> SessionResults {
+        let score = session.score();
+        SessionResults {
+            quiz: quiz_file.to_string(),
+            date: date_string(today),
+            seed: session.seed(),
+            number_right: score.number_right,
+            number_wrong: score.number_wrong,
+            categories: session.category_scores(),
+            missed_slides: session.missed_slides(),
+            questions: session.results().to_vec(),
+        }
+    }
+}
+
+//A CSV file is just rows, so each one repeats the session it came from.
+#[derive(Serialize)]
+struct ResultRow<'a> {
+    quiz: &'a str,
+    date: &'a str,
+    seed: u64,
+    slide: &'a str,
+    line: u64,
+    category: &'a str,
+    prompt: &'a str,
+    choices: String,
+    picked: &'a str,
+    right_answer: &'a str,
+    correct: bool,
+    attempts: u32,
+    seconds: f64,
+}
+
+//The breakdown that goes next to a CSV results file, one row per category and then one per missed slide.
+#[derive(Serialize)]
+struct SummaryRow<'a> {
+    kind: &'a str,
+    name: &'a str,
+    slide: Option<&'a str>,
+    line: Option<u64>,
+    number_right: u32,
+    number_wrong: u32,
+}
+
+impl<'a> SummaryRow<'a> {
+    fn category(category: &'a CategoryScore) -> SummaryRow<'a> {
+        SummaryRow::new("category", &category.category, None, None, category.score)
+    }
+
+    fn slide(slide: &'a MissedSlide) -> SummaryRow<'a> {
+        SummaryRow::new(
+            "slide",
+            &slide.name,
+            Some(&slide.slide),
+            Some(slide.line),
+            slide.score,
+        )
+    }
+
+    fn new(
+        kind: &'a str,
+        name: &'a str,
+        slide: Option<&'a str>,
+        line: Option<u64>,
+        score: Score,
+    ) -> SummaryRow<'a> {
+        SummaryRow {
+            kind,
+            name,
+            slide,
+            line,
+            number_right: score.number_right,
+            number_wrong: score.number_wrong,
+        }
+    }
+}
+
+//Where the breakdown of a CSV results file goes, like results.summary.csv for results.csv.
+pub fn summary_path(path: &Path) -> PathBuf {
+    path.with_extension("summary.csv")
+}
+
+//Writes one row per question for .csv files, with the breakdown in summary_path, and the whole thing for .json files.
+pub fn write_results(results: &SessionResults, path: &Path) -> io::Result<()> {
+    match DeckFormat::from_path(path) {
+        DeckFormat::Json => {
+            let text = serde_json::to_string_pretty(results).map_err(io::Error::from)?;
+            fs::write(path, text)
+        }
+        DeckFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            for question in results.questions.iter() {
+                writer.serialize(ResultRow {
+                    quiz: &results.quiz,
+                    date: &results.date,
+                    seed: results.seed,
+                    slide: &question.slide,
+                    line: question.line,
+                    category: &question.category,
+                    prompt: &question.prompt,
+                    choices: question.choices.join(&ALIAS_SEPARATOR.to_string()),
+                    picked: &question.picked,
+                    right_answer: &question.right_answer,
+                    correct: question.correct,
+                    attempts: question.attempts,
+                    seconds: question.seconds,
+                })?;
+            }
+            writer.flush()?;
+
+            let mut summary = csv::Writer::from_path(summary_path(path))?;
+            let rows = results
+                .categories
+                .iter()
+                .map(SummaryRow::category)
+                .chain(results.missed_slides.iter().map(SummaryRow::slide));
+            for row in rows {
+                summary.serialize(row)?;
+            }
+            summary.flush()
+        }
+        DeckFormat::Toml => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "results can only be written as .csv or .json",
+        )),
+    }
+}
+
+//YYYY-MM-DD for a number of days since the Unix epoch, so spreadsheets can read it as a date.
+fn date_string(days: u64) -> String {
+    //Counts from March so the leap day is at the end of the year. See Howard Hinnant's civil_from_days.
+    let days = days as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn results() -> SessionResults {
+        SessionResults {
+            quiz: "capitals.csv".to_string(),
+            date: date_string(0),
+            seed: 5,
+            number_right: 0,
+            number_wrong: 1,
+            categories: Vec::new(),
+            missed_slides: Vec::new(),
+            questions: vec![QuestionResult {
+                slide: "France".to_string(),
+                line: 2,
+                category: "Capital".to_string(),
+                prompt: "Capital?".to_string(),
+                choices: vec!["Paris".to_string(), "Rome".to_string()],
+                picked: "Rome".to_string(),
+                right_answer: "Paris".to_string(),
+                correct: false,
+                attempts: 2,
+                seconds: 1.5,
+            }],
+        }
+    }
+
+    #[test]
+    fn date_string_counts_from_the_epoch() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(59), "1970-03-01");
+        assert_eq!(date_string(11_016), "2000-02-29");
+        assert_eq!(date_string(11_017), "2000-03-01");
+        assert_eq!(date_string(19_722), "2023-12-31");
+    }
+
+    #[test]
+    fn both_formats_keep_attempts() {
+        let base = env::temp_dir().join(format!("rusty_quiz_{}_results", std::process::id()));
+
+        let csv = base.with_extension("csv");
+        write_results(&results(), &csv).unwrap();
+        let text = fs::read_to_string(&csv).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some("quiz,date,seed,slide,line,category,prompt,choices,picked,right_answer,correct,attempts,seconds")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("capitals.csv,1970-01-01,5,France,2,Capital,Capital?,Paris|Rome,Rome,Paris,false,2,1.5")
+        );
+        fs::remove_file(&csv).unwrap();
+        fs::remove_file(summary_path(&csv)).unwrap();
+
+        let json = base.with_extension("json");
+        write_results(&results(), &json).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(value["questions"][0]["attempts"], 2);
+        fs::remove_file(&json).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+use deck::{Quiz, Slide};
+use session::Review;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STARTING_EASE: f32 = 2.5;
+const MINIMUM_EASE: f32 = 1.3;
+
+//SM-2 grades go from 0 to 5. Only the first guess is known, so a card is either remembered or forgotten.
+const GRADE_RIGHT: u32 = 4;
+const GRADE_WRONG: u32 = 1;
+
+//Days since the Unix epoch, in UTC.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / (24 * 60 * 60))
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CardProgress {
+    pub ease: f32,
+    //Days until the card is asked again.
+    pub interval: u64,
+    //Reviews in a row that were remembered.
+    pub repetitions: u32,
+    //The day the card is next asked, as returned by today().
+    pub due: u64,
+}
+
+impl Default for CardProgress {
+    fn default() -> CardProgress {
+        CardProgress {
+            ease: STARTING_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: 0,
+        }
+    }
+}
+
+impl CardProgress {
+    pub fn review(&mut self, grade: u32, today: u64) {
+        if grade >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f32 * self.ease).round() as u64,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval = 1;
+        }
+
+        let missed = 5.0 - grade as f32;
+        self.ease = (self.ease + 0.1 - missed * (0.08 + missed * 0.02)).max(MINIMUM_EASE);
+        self.due = today + self.interval;
+    }
+}
+
+//Progress for every card the user has seen, keyed by slide and then question category.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    pub cards: BTreeMap<String, BTreeMap<String, CardProgress>>,
+}
+
+//Identifies each slide across runs, even if rows are moved around in the quiz file.
+//The answers are always part of it, since slides often share hints like "Europe".
+//Slides that are the same in every way are told apart by a number, counting down the file.
+pub fn slide_keys(slides: &[Slide]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    slides
+        .iter()
+        .map(|slide| {
+            let mut parts: Vec<String> = Vec::new();
+            if let Some(ref path) = slide.image_path {
+                parts.push(path.to_string_lossy().into_owned());
+            }
+            parts.extend(slide.hints.iter().filter(|h| !h.is_empty()).cloned());
+            parts.extend(slide.answers.iter().cloned());
+            let key = parts.join(" | ");
+
+            let count = seen.entry(key.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                key
+            } else {
+                format!("{} #{}", key, count)
+            }
+        })
+        .collect()
+}
+
+impl Schedule {
+    //A missing file is just a schedule with nothing seen yet.
+    pub fn load(path: &Path) -> io::Result<Schedule> {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(file).map_err(io::Error::from),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Schedule::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::from)
+    }
+
+    pub fn card(&self, quiz: &Quiz, slide: usize, category: usize) -> CardProgress {
+        self.cards
+            .get(&quiz.slide_keys[slide])
+            .and_then(|c| c.get(&quiz.header.questions[category]))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    //Cards that have never been seen are always due.
+    pub fn is_due(&self, quiz: &Quiz, slide: usize, category: usize, today: u64) -> bool {
+        self.card(quiz, slide, category).due <= today
+    }
+
+    pub fn review(&mut self, quiz: &Quiz, review: &Review, today: u64) {
+        let grade = if review.right_first_time {
+            GRADE_RIGHT
+        } else {
+            GRADE_WRONG
+        };
+        self.cards
+            .entry(quiz.slide_keys[review.slide].clone())
+            .or_default()
+            .entry(quiz.header.questions[review.category].clone())
+            .or_default()
+            .review(grade, today);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deck::{Header, Metadata};
+    use question::{AnswerMode, DEFAULT_CHOICES};
+
+    #[test]
+    fn remembered_cards_wait_longer_each_time() {
+        let mut card = CardProgress::default();
+        card.review(GRADE_RIGHT, 100);
+        assert_eq!((card.interval, card.repetitions, card.due), (1, 1, 101));
+        card.review(GRADE_RIGHT, 101);
+        assert_eq!((card.interval, card.repetitions, card.due), (6, 2, 107));
+        card.review(GRADE_RIGHT, 107);
+        assert_eq!(card.interval, (6.0 * card.ease).round() as u64);
+        assert_eq!(card.due, 107 + card.interval);
+        assert_eq!(card.ease, STARTING_EASE);
+    }
+
+    #[test]
+    fn forgotten_cards_start_over() {
+        let mut card = CardProgress::default();
+        card.review(GRADE_RIGHT, 0);
+        card.review(GRADE_RIGHT, 1);
+        card.review(GRADE_WRONG, 7);
+        assert_eq!((card.interval, card.repetitions, card.due), (1, 0, 8));
+        assert!(card.ease < STARTING_EASE);
+
+        for day in 8..20 {
+            card.review(GRADE_WRONG, day);
+        }
+        assert_eq!(card.ease, MINIMUM_EASE);
+    }
+
+    fn slide(hint: &str, answer: &str) -> Slide {
+        Slide {
+            line: 0,
+            image_path: None,
+            image: None,
+            hints: vec![hint.to_string()],
+            answers: vec![answer.to_string()],
+            aliases: Vec::new(),
+            explanations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn slides_sharing_a_hint_get_their_own_keys() {
+        let slides = vec![
+            slide("Europe", "France"),
+            slide("Europe", "Germany"),
+            slide("", "Spain"),
+            slide("Europe", "France"),
+        ];
+        assert_eq!(
+            slide_keys(&slides),
+            vec![
+                "Europe | France",
+                "Europe | Germany",
+                "Spain",
+                "Europe | France #2"
+            ]
+        );
+    }
+
+    #[test]
+    fn slides_sharing_a_hint_keep_their_own_progress() {
+        let slides = vec![slide("Europe", "France"), slide("Europe", "Germany")];
+        let quiz = Quiz {
+            header: Header::new(vec![None], vec!["Country".to_string()]),
+            slide_keys: slide_keys(&slides),
+            slides,
+            metadata: Metadata::default(),
+            choices: DEFAULT_CHOICES,
+            mode: AnswerMode::MultipleChoice,
+            cross: None,
+            tolerance: None,
+        };
+        let mut schedule = Schedule::default();
+        for &(slide, right_first_time) in [(0, true), (1, false)].iter() {
+            let review = Review {
+                slide,
+                category: 0,
+                right_first_time,
+            };
+            schedule.review(&quiz, &review, 10);
+        }
+
+        assert_eq!(schedule.cards.len(), 2);
+        assert!(schedule.card(&quiz, 0, 0).ease > schedule.card(&quiz, 1, 0).ease);
+    }
+}
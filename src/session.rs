@@ -0,0 +1,491 @@
+use deck::{Quiz, Slide};
+use question::{
+    generate_cross_question, generate_question, generate_reverse_question, slide_label, AnswerMode,
+    Question,
+};
+use rand::prng::ChaChaRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::time::Instant;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Guess {
+    Right,
+    Wrong,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Progress {
+    NextQuestion,
+    NextSlide,
+    Finished,
+}
+
+#[derive(Clone, Copy, Default, Debug, Serialize)]
+pub struct Score {
+    pub number_right: u32,
+    pub number_wrong: u32,
+}
+
+impl Score {
+    //0 when nothing has been answered yet.
+    pub fn percent_correct(&self) -> f32 {
+        let total = self.number_right + self.number_wrong;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.number_right as f32 / total as f32) * 100.0
+    }
+
+    fn add(&mut self, right: bool) {
+        if right {
+            self.number_right += 1;
+        } else {
+            self.number_wrong += 1;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CategoryScore {
+    pub category: String,
+    pub score: Score,
+}
+
+//A slide that had at least one of its questions answered wrong.
+#[derive(Clone, Debug, Serialize)]
+pub struct MissedSlide {
+    //The same key the progress file uses for the slide.
+    pub slide: String,
+    //What the slide is called when it isn't shown, like in reverse questions.
+    pub name: String,
+    pub line: u64,
+    pub score: Score,
+}
+
+//How the first guess at a question went, for scheduling the card again.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Review {
+    pub slide: usize,
+    pub category: usize,
+    pub right_first_time: bool,
+}
+
+//One question as it was asked and answered, for the results file.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct QuestionResult {
+    //The same key the progress file uses for the slide.
+    pub slide: String,
+    pub line: u64,
+    pub category: String,
+    pub prompt: String,
+    //Empty for typed answers.
+    pub choices: Vec<String>,
+    //The first answer given, which is the one that counts.
+    pub picked: String,
+    pub right_answer: String,
+    pub correct: bool,
+    //Every guess made, counting the first. Only the first is scored, but answer and answer_text take more.
+    pub attempts: u32,
+    //From the question being asked to the first answer.
+    pub seconds: f64,
+}
+
+//Walks through the slides in a random order, asking each of their question categories in turn.
+//Only the first guess of a question counts towards the score.
+//All the randomness comes from one seed, so a session started with the same seed, quiz and cards plays out the same.
+pub struct Session<'a> {
+    quiz: &'a Quiz,
+    seed: u64,
+    //ChaCha rather than StdRng, which is allowed to change between rand versions.
+    rng: ChaChaRng,
+    //(slide, category) pairs in the order they are asked.
+    cards: Vec<(usize, usize)>,
+    position: usize,
+    question: Option<Question>,
+    already_guessed: bool,
+    score: Score,
+    reviews: Vec<Review>,
+    results: Vec<QuestionResult>,
+    asked_at: Instant,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(quiz: &'a Quiz) -> Session<'a> {
+        Session::filtered(quiz, |_, _| true)
+    }
+
+    //Only asks the questions `keep` returns true for, given the slide and category index.
+    pub fn filtered<F: Fn(usize, usize) -> bool>(quiz: &'a Quiz, keep: F) -> Session<'a> {
+        Session::seeded(quiz, thread_rng().gen(), keep)
+    }
+
+    //Like filtered, but with a given seed instead of a random one.
+    pub fn seeded<F: Fn(usize, usize) -> bool>(quiz: &'a Quiz, seed: u64, keep: F) -> Session<'a> {
+        let mut rng = seeded_rng(seed);
+        let mut order: Vec<usize> = (0..quiz.slides.len()).collect();
+        rng.shuffle(&mut order);
+
+        //Cross questions only ask for the target, and only on slides with something to prompt with and something to ask for.
+        let cross = match quiz.mode {
+            AnswerMode::Reverse => None,
+            AnswerMode::MultipleChoice | AnswerMode::Typed => quiz.cross,
+        };
+        let asked = |slide: usize, category: usize| match cross {
+            Some(cross) => {
+                let answers = &quiz.slides[slide].answers;
+                category == cross.target
+                    && answers.get(cross.prompt).is_some_and(|a| !a.is_empty())
+                    && answers.get(cross.target).is_some_and(|a| !a.is_empty())
+            }
+            None => true,
+        };
+
+        let mut cards = Vec::new();
+        for slide in order {
+            for category in 0..quiz.header.questions.len() {
+                if asked(slide, category) && keep(slide, category) {
+                    cards.push((slide, category));
+                }
+            }
+        }
+
+        let mut session = Session {
+            quiz,
+            seed,
+            rng,
+            cards,
+            position: 0,
+            question: None,
+            already_guessed: false,
+            score: Score::default(),
+            reviews: Vec::new(),
+            results: Vec::new(),
+            asked_at: Instant::now(),
+        };
+        session.ask();
+        session
+    }
+
+    pub fn current_slide(&self) -> Option<&'a Slide> {
+        let quiz = self.quiz;
+        self.cards.get(self.position).map(|&(i, _)| &quiz.slides[i])
+    }
+
+    pub fn current_question(&self) -> Option<&Question> {
+        self.question.as_ref()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.question.is_none()
+    }
+
+    pub fn number_of_questions(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    //The seed to pass to seeded to get this session again.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn reviews(&self) -> &[Review] {
+        &self.reviews
+    }
+
+    //Scores for each category that had a question answered, in the order of header.questions.
+    pub fn category_scores(&self) -> Vec<CategoryScore> {
+        let mut scores: Vec<Option<Score>> = vec![None; self.quiz.header.questions.len()];
+        for review in self.reviews.iter() {
+            scores[review.category]
+                .get_or_insert_with(Score::default)
+                .add(review.right_first_time);
+        }
+        scores
+            .into_iter()
+            .enumerate()
+            .filter_map(|(category, score)| {
+                score.map(|score| CategoryScore {
+                    category: self.quiz.header.questions[category].clone(),
+                    score,
+                })
+            })
+            .collect()
+    }
+
+    //Most wrong answers first. Ties go to the slide with fewer right answers, then to the one earlier in the file.
+    pub fn missed_slides(&self) -> Vec<MissedSlide> {
+        let mut scores: Vec<Score> = vec![Score::default(); self.quiz.slides.len()];
+        for review in self.reviews.iter() {
+            scores[review.slide].add(review.right_first_time);
+        }
+
+        let quiz = self.quiz;
+        let mut missed: Vec<MissedSlide> = scores
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, score)| score.number_wrong > 0)
+            .map(|(i, score)| MissedSlide {
+                slide: quiz.slide_keys[i].clone(),
+                name: slide_label(&quiz.header, &quiz.slides[i]),
+                line: quiz.slides[i].line,
+                score,
+            })
+            .collect();
+        missed.sort_by_key(|m| (Reverse(m.score.number_wrong), m.score.number_right, m.line));
+        missed
+    }
+
+    //Only questions that were answered are in here.
+    pub fn results(&self) -> &[QuestionResult] {
+        &self.results
+    }
+
+    pub fn answer(&mut self, choice: usize) -> Guess {
+        let (right, picked) = match self.question {
+            Some(ref question) => (
+                question.is_right(choice),
+                question.choices.get(choice).cloned().unwrap_or_default(),
+            ),
+            None => return Guess::Wrong,
+        };
+        self.guess(right, picked)
+    }
+
+    //For AnswerMode::Typed, using the quiz's tolerance for typos.
+    pub fn answer_text(&mut self, typed: &str) -> Guess {
+        let right = match self.question {
+            Some(ref question) => question.is_right_text(typed, self.quiz.tolerance),
+            None => return Guess::Wrong,
+        };
+        self.guess(right, typed.to_string())
+    }
+
+    fn guess(&mut self, right: bool, picked: String) -> Guess {
+        if !self.already_guessed {
+            self.already_guessed = true;
+            self.score.add(right);
+
+            let (slide, category) = self.cards[self.position];
+            self.reviews.push(Review {
+                slide,
+                category,
+                right_first_time: right,
+            });
+
+            let quiz = self.quiz;
+            let question = self.question.as_ref().unwrap();
+            let typed = quiz.mode == AnswerMode::Typed;
+            self.results.push(QuestionResult {
+                slide: quiz.slide_keys[slide].clone(),
+                line: quiz.slides[slide].line,
+                category: quiz.header.questions[category].clone(),
+                prompt: question.prompt.clone(),
+                choices: if typed {
+                    Vec::new()
+                } else {
+                    question.choices.clone()
+                },
+                picked,
+                right_answer: question.choices[question.right_answer].clone(),
+                correct: right,
+                attempts: 1,
+                seconds: self.asked_at.elapsed().as_secs_f64(),
+            });
+        } else if let Some(result) = self.results.last_mut() {
+            result.attempts += 1;
+        }
+
+        if right {
+            Guess::Right
+        } else {
+            Guess::Wrong
+        }
+    }
+
+    //Restarts the clock for the current question's seconds, for front ends that take a while to show it after it is asked.
+    pub fn start_timer(&mut self) {
+        self.asked_at = Instant::now();
+    }
+
+    pub fn advance(&mut self) -> Progress {
+        let slide = match self.cards.get(self.position) {
+            Some(&(slide, _)) => slide,
+            None => return Progress::Finished,
+        };
+
+        self.position += 1;
+        self.ask();
+        match self.cards.get(self.position) {
+            None => Progress::Finished,
+            Some(&(next_slide, _)) if next_slide == slide => Progress::NextQuestion,
+            Some(_) => Progress::NextSlide,
+        }
+    }
+
+    fn ask(&mut self) {
+        self.already_guessed = false;
+        self.asked_at = Instant::now();
+        let quiz = self.quiz;
+        let rng = &mut self.rng;
+        self.question =
+            self.cards
+                .get(self.position)
+                .map(|&(slide, category)| match (quiz.mode, quiz.cross) {
+                    (AnswerMode::Reverse, _) => generate_reverse_question(
+                        rng,
+                        category,
+                        &quiz.header,
+                        slide,
+                        &quiz.slides,
+                        quiz.choices,
+                    ),
+                    (_, Some(cross)) => generate_cross_question(
+                        rng,
+                        cross,
+                        &quiz.header,
+                        &quiz.slides[slide],
+                        &quiz.slides,
+                        quiz.choices,
+                    ),
+                    (AnswerMode::MultipleChoice, None) | (AnswerMode::Typed, None) => {
+                        generate_question(
+                            rng,
+                            category,
+                            &quiz.header,
+                            &quiz.slides[slide],
+                            &quiz.slides,
+                            quiz.choices,
+                        )
+                    }
+                });
+    }
+}
+
+//The seed goes in the first bytes of ChaCha's key and the rest are left as zeros.
+fn seeded_rng(seed: u64) -> ChaChaRng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    ChaChaRng::from_seed(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deck::{Header, Metadata};
+    use question::DEFAULT_CHOICES;
+    use schedule::slide_keys;
+    use std::thread;
+    use std::time::Duration;
+
+    fn quiz(mode: AnswerMode) -> Quiz {
+        let rows = [
+            ("France", "Paris", "Euro"),
+            ("Germany", "Berlin", "Euro"),
+            ("Japan", "Tokyo", "Yen"),
+            ("United Kingdom", "London", "Pound"),
+            ("Switzerland", "Bern", "Franc"),
+            ("United States", "Washington", "Dollar"),
+        ];
+        let slides: Vec<Slide> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, &(country, capital, currency))| Slide {
+                line: i as u64 + 2,
+                image_path: None,
+                image: None,
+                hints: vec![country.to_string()],
+                answers: vec![capital.to_string(), currency.to_string()],
+                aliases: vec![Vec::new(), Vec::new()],
+                explanations: Vec::new(),
+            })
+            .collect();
+        Quiz {
+            header: Header::new(
+                vec![None],
+                vec!["Capital".to_string(), "Currency".to_string()],
+            ),
+            slide_keys: slide_keys(&slides),
+            slides,
+            metadata: Metadata::default(),
+            choices: DEFAULT_CHOICES,
+            mode,
+            cross: None,
+            tolerance: None,
+        }
+    }
+
+    //Every question asked, answering the first choice each time.
+    fn play(quiz: &Quiz, seed: u64) -> Vec<(u64, String, Vec<String>, usize)> {
+        let mut session = Session::seeded(quiz, seed, |_, _| true);
+        let mut asked = Vec::new();
+        while let Some(question) = session.current_question() {
+            asked.push((
+                session.current_slide().unwrap().line,
+                question.prompt.clone(),
+                question.choices.clone(),
+                question.right_answer,
+            ));
+            session.answer(0);
+            session.advance();
+        }
+        asked
+    }
+
+    #[test]
+    fn the_same_seed_asks_the_same_questions() {
+        for &mode in [AnswerMode::MultipleChoice, AnswerMode::Reverse].iter() {
+            let quiz = quiz(mode);
+            let asked = play(&quiz, 42);
+            assert_eq!(asked.len(), 12);
+            assert_eq!(asked, play(&quiz, 42));
+            assert_ne!(asked, play(&quiz, 43));
+        }
+    }
+
+    #[test]
+    fn every_guess_counts_as_an_attempt() {
+        let quiz = quiz(AnswerMode::MultipleChoice);
+        let mut session = Session::seeded(&quiz, 7, |_, _| true);
+        let right = session.current_question().unwrap().right_answer;
+        let wrong = (right + 1) % DEFAULT_CHOICES;
+        assert_eq!(session.answer(wrong), Guess::Wrong);
+        assert_eq!(session.answer(wrong), Guess::Wrong);
+        assert_eq!(session.answer(right), Guess::Right);
+        session.advance();
+        session.answer(session.current_question().unwrap().right_answer);
+
+        let results = session.results();
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].correct, results[0].attempts), (false, 3));
+        assert_eq!((results[1].correct, results[1].attempts), (true, 1));
+        assert_eq!(
+            (session.score().number_right, session.score().number_wrong),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn start_timer_leaves_out_the_time_before_the_question_is_shown() {
+        let quiz = quiz(AnswerMode::MultipleChoice);
+        let mut session = Session::seeded(&quiz, 7, |_, _| true);
+        thread::sleep(Duration::from_millis(50));
+        session.start_timer();
+        session.answer(0);
+        assert!(session.results()[0].seconds < 0.05);
+    }
+
+    #[test]
+    fn percent_correct_is_zero_before_any_answers() {
+        assert_eq!(Score::default().percent_correct(), 0.0);
+        let score = Score {
+            number_right: 3,
+            number_wrong: 1,
+        };
+        assert_eq!(score.percent_correct(), 75.0);
+    }
+}
@@ -0,0 +1,583 @@
+use atlas::{Region, TextureAtlas};
+use gl::types::*;
+use image::RgbaImage;
+use rusttype::{point, Font, GlyphId, PositionedGlyph, Scale};
+use rusty_quiz::markup::{parse_emphasis, Style, StyledChar};
+use std::collections::HashMap;
+
+use add_textured_box;
+
+//Space left around the text on every side.
+const PADDING: f32 = 10.0;
+//How far italics lean when there is no italic font, in pixels across for each pixel up.
+const SLANT: f32 = 0.2;
+//Glyphs in the atlas are drawn at quarter pixel steps across, so text anywhere looks the same as it would rendered on its own.
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+//Where each line goes within the widest one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+//The regular fonts in the order they are tried for each character, the first being the primary one.
+//Bold and italic text tries its own fonts first and fakes the style with a regular one if none of them have the character.
+//The chains hold indexes into `fonts`, which is how glyphs from the same font are told apart.
+pub struct Fonts {
+    fonts: Vec<Font<'static>>,
+    regular: Vec<usize>,
+    bold: Vec<usize>,
+    italic: Vec<usize>,
+}
+
+fn has_glyph(font: &Font, c: char) -> bool {
+    font.glyph(c).id() != GlyphId(0)
+}
+
+impl Fonts {
+    pub fn new(
+        regular: Vec<Font<'static>>,
+        bold: Vec<Font<'static>>,
+        italic: Vec<Font<'static>>,
+    ) -> Fonts {
+        assert!(!regular.is_empty(), "There has to be a regular font!");
+        let mut fonts = Vec::new();
+        let mut chain = |list: Vec<Font<'static>>| {
+            let start = fonts.len();
+            fonts.extend(list);
+            (start..fonts.len()).collect()
+        };
+        let regular = chain(regular);
+        let bold = chain(bold);
+        let italic = chain(italic);
+        Fonts {
+            fonts,
+            regular,
+            bold,
+            italic,
+        }
+    }
+
+    //Also returns the parts of the style the font doesn't have, which have to be faked.
+    //When no font has the character, the primary font's placeholder box is used.
+    fn font_for(&self, c: char, style: Style) -> (usize, Style) {
+        let mut chains: Vec<(&[usize], Style)> = Vec::new();
+        if style.bold {
+            chains.push((
+                &self.bold,
+                Style {
+                    bold: false,
+                    ..style
+                },
+            ));
+        }
+        if style.italic {
+            chains.push((
+                &self.italic,
+                Style {
+                    italic: false,
+                    ..style
+                },
+            ));
+        }
+        chains.push((&self.regular, style));
+
+        for (chain, fake) in chains {
+            if let Some(&font) = chain.iter().find(|&&f| has_glyph(&self.fonts[f], c)) {
+                return (font, fake);
+            }
+        }
+        (self.regular[0], style)
+    }
+
+    fn primary(&self) -> &Font<'static> {
+        &self.fonts[self.regular[0]]
+    }
+}
+
+//Characters none of the regular fonts have, which show up as the primary font's placeholder box.
+pub fn missing_glyphs(fonts: &Fonts, text: &str) -> Vec<char> {
+    let mut missing = Vec::new();
+    for c in text.chars() {
+        if c.is_whitespace() || c.is_control() || missing.contains(&c) {
+            continue;
+        }
+        if !fonts.regular.iter().any(|&f| has_glyph(&fonts.fonts[f], c)) {
+            missing.push(c);
+        }
+    }
+    missing
+}
+
+//A glyph, the font it came from and the parts of its style that font couldn't do.
+struct PlacedGlyph {
+    glyph: PositionedGlyph<'static>,
+    font: usize,
+    fake: Style,
+}
+
+//Each character gets the first font that has it. Kerning only applies between characters from the same font.
+//Also returns how far the pen moved.
+fn place(
+    fonts: &Fonts,
+    scale: Scale,
+    chars: &[StyledChar],
+    x: f32,
+    baseline: f32,
+) -> (Vec<PlacedGlyph>, f32) {
+    let mut glyphs = Vec::new();
+    let mut caret = x;
+    let mut last: Option<(usize, GlyphId)> = None;
+    for &(c, style) in chars {
+        let (font, fake) = fonts.font_for(c, style);
+        let glyph = fonts.fonts[font].glyph(c).scaled(scale);
+        if let Some((last_font, last_id)) = last {
+            if last_font == font {
+                caret += fonts.fonts[font].pair_kerning(scale, last_id, glyph.id());
+            }
+        }
+        last = Some((font, glyph.id()));
+
+        let advance = glyph.h_metrics().advance_width;
+        glyphs.push(PlacedGlyph {
+            glyph: glyph.positioned(point(caret, baseline)),
+            font,
+            fake,
+        });
+        caret += advance;
+    }
+    (glyphs, caret - x)
+}
+
+fn text_width(fonts: &Fonts, scale: Scale, chars: &[StyledChar]) -> f32 {
+    place(fonts, scale, chars, 0.0, 0.0).1
+}
+
+//Tabs become spaces and other control characters are dropped, since the fonts have nothing to draw for them.
+fn printable(paragraph: &[StyledChar]) -> Vec<StyledChar> {
+    paragraph
+        .iter()
+        .filter_map(|&(c, style)| match c {
+            '\t' => Some((' ', style)),
+            c if c.is_control() => None,
+            c => Some((c, style)),
+        })
+        .collect()
+}
+
+//Breaks at spaces where it can and inside a word when the word is too wide on its own.
+//Spaces where a line is broken are dropped, the rest are kept.
+fn wrap(
+    fonts: &Fonts,
+    scale: Scale,
+    paragraph: &[StyledChar],
+    max_width: f32,
+) -> Vec<Vec<StyledChar>> {
+    let mut lines = Vec::new();
+    let mut line: Option<Vec<StyledChar>> = None;
+    for word in paragraph.split(|&(c, _)| c == ' ') {
+        let candidate = match line {
+            Some(ref line) => {
+                let mut candidate = line.clone();
+                candidate.push((' ', Style::default()));
+                candidate.extend_from_slice(word);
+                candidate
+            }
+            None => word.to_vec(),
+        };
+        if text_width(fonts, scale, &candidate) <= max_width {
+            line = Some(candidate);
+            continue;
+        }
+
+        lines.extend(line.take());
+        let mut current: Vec<StyledChar> = Vec::new();
+        for &c in word {
+            current.push(c);
+            if current.len() > 1 && text_width(fonts, scale, &current) > max_width {
+                current.pop();
+                lines.push(current);
+                current = vec![c];
+            }
+        }
+        line = Some(current);
+    }
+    lines.extend(line);
+    lines
+}
+
+//Positions are in pixels from the top left of the image, padding included.
+pub struct LineMetrics {
+    pub x: f32,
+    pub baseline: f32,
+    pub width: f32,
+    chars: Vec<StyledChar>,
+}
+
+pub struct TextLayout {
+    pub scale: Scale,
+    pub lines: Vec<LineMetrics>,
+    //The size of the image the text is rendered into.
+    pub width: u32,
+    pub height: u32,
+}
+
+//Starts a new line at each line break, and wraps lines wider than `max_width` if there is one.
+//Lines are spaced using the primary font.
+fn layout_chars(
+    fonts: &Fonts,
+    scale: f32,
+    chars: &[StyledChar],
+    max_width: Option<f32>,
+    alignment: Alignment,
+) -> TextLayout {
+    let scale = Scale::uniform(scale);
+    let v_metrics = fonts.primary().v_metrics(scale);
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    let mut lines: Vec<LineMetrics> = Vec::new();
+    //An empty string still gets one empty line, so there is always an image to draw.
+    for paragraph in chars.split(|&(c, _)| c == '\n').map(printable) {
+        let wrapped = match max_width {
+            Some(max_width) => wrap(fonts, scale, &paragraph, max_width),
+            None => vec![paragraph],
+        };
+        for line in wrapped {
+            lines.push(LineMetrics {
+                x: PADDING,
+                baseline: PADDING + v_metrics.ascent + lines.len() as f32 * line_height,
+                width: text_width(fonts, scale, &line),
+                chars: line,
+            });
+        }
+    }
+
+    let text_width = lines.iter().map(|l| l.width).fold(0.0, f32::max);
+    for line in lines.iter_mut() {
+        line.x += match alignment {
+            Alignment::Left => 0.0,
+            Alignment::Center => ((text_width - line.width) / 2.0).floor(),
+            Alignment::Right => (text_width - line.width).floor(),
+        };
+    }
+
+    let text_height = v_metrics.ascent - v_metrics.descent + (lines.len() - 1) as f32 * line_height;
+    TextLayout {
+        scale,
+        lines,
+        width: text_width.ceil() as u32 + 2 * PADDING as u32,
+        height: text_height.ceil() as u32 + 2 * PADDING as u32,
+    }
+}
+
+//The text as it is, asterisks and all.
+pub fn layout_text(
+    fonts: &Fonts,
+    scale: f32,
+    text: &str,
+    max_width: Option<f32>,
+    alignment: Alignment,
+) -> TextLayout {
+    let chars: Vec<StyledChar> = text.chars().map(|c| (c, Style::default())).collect();
+    layout_chars(fonts, scale, &chars, max_width, alignment)
+}
+
+//Like layout_text, with the emphasis from parse_emphasis.
+pub fn layout_markup(
+    fonts: &Fonts,
+    scale: f32,
+    text: &str,
+    max_width: Option<f32>,
+    alignment: Alignment,
+) -> TextLayout {
+    layout_chars(fonts, scale, &parse_emphasis(text), max_width, alignment)
+}
+
+//White on a clear background, along with where its top left corner goes. None for glyphs with nothing to draw, like spaces.
+//Fake bold draws the glyph twice, a pixel apart, and fake italic shifts each row by how far it is above `baseline`.
+fn rasterize(
+    glyph: &PositionedGlyph,
+    fake: Style,
+    baseline: f32,
+) -> Option<(RgbaImage, (i32, i32))> {
+    let bounding_box = glyph.pixel_bounding_box()?;
+    let slant = |y: i32| {
+        if fake.italic {
+            ((baseline - y as f32) * SLANT).round() as i32
+        } else {
+            0
+        }
+    };
+    //The bottom row leans the least and the top row the most.
+    let (lowest, highest) = (slant(bounding_box.max.y - 1), slant(bounding_box.min.y));
+    let strokes = if fake.bold { 2 } else { 1 };
+
+    let mut image = RgbaImage::new(
+        (bounding_box.width() + highest - lowest + strokes - 1) as u32,
+        bounding_box.height() as u32,
+    );
+    glyph.draw(|x, y, v| {
+        let x = x as i32 + slant(y as i32 + bounding_box.min.y) - lowest;
+        for stroke in 0..strokes {
+            let pixel = image.get_pixel_mut((x + stroke) as u32, y);
+            pixel.data[3] = pixel.data[3].max((v * 255.0) as u8);
+            pixel.data[0] = 255;
+            pixel.data[1] = 255;
+            pixel.data[2] = 255;
+        }
+    });
+    Some((image, (bounding_box.min.x + lowest, bounding_box.min.y)))
+}
+
+//White text on a clear background, to be colored when it is drawn.
+pub fn render_layout(fonts: &Fonts, layout: &TextLayout) -> RgbaImage {
+    let mut image = RgbaImage::new(layout.width, layout.height);
+
+    for line in layout.lines.iter() {
+        let (glyphs, _) = place(fonts, layout.scale, &line.chars, line.x, line.baseline);
+        for placed in glyphs {
+            let (glyph_image, corner) = match rasterize(&placed.glyph, placed.fake, line.baseline) {
+                Some(rasterized) => rasterized,
+                None => continue,
+            };
+            for (x, y, glyph_pixel) in glyph_image.enumerate_pixels() {
+                let x = corner.0 + x as i32;
+                let y = corner.1 + y as i32;
+                if x < 0 || y < 0 || x >= layout.width as i32 || y >= layout.height as i32 {
+                    continue;
+                }
+                //Neighbouring glyphs can overlap a little, so keep whichever is more opaque.
+                let pixel = image.get_pixel_mut(x as u32, y as u32);
+                if glyph_pixel.data[3] > pixel.data[3] {
+                    *pixel = *glyph_pixel;
+                }
+            }
+        }
+    }
+
+    image
+}
+
+//On one line, or more if the text has line breaks. Asterisks are shown as they are.
+pub fn render_text(fonts: &Fonts, scale: f32, text: &str) -> RgbaImage {
+    render_layout(
+        fonts,
+        &layout_text(fonts, scale, text, None, Alignment::Left),
+    )
+}
+
+//Quiz content, wrapped and with emphasis.
+pub fn render_markup(
+    fonts: &Fonts,
+    scale: f32,
+    text: &str,
+    max_width: f32,
+    alignment: Alignment,
+) -> RgbaImage {
+    render_layout(
+        fonts,
+        &layout_markup(fonts, scale, text, Some(max_width), alignment),
+    )
+}
+
+//Everything that changes how a glyph is drawn into the atlas.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    id: GlyphId,
+    //The bits of the scale, since floats can't be hashed.
+    scale: u32,
+    fake: Style,
+    subpixel: u8,
+}
+
+//A glyph in the atlas and where its top left corner goes, from the pen on the baseline.
+#[derive(Clone, Copy)]
+struct CachedGlyph {
+    region: Region,
+    corner: (i32, i32),
+}
+
+//Glyphs that have been drawn into the atlas already, so text can be drawn every frame without rasterizing it again.
+//None is kept for glyphs with nothing to draw. It all goes when the atlas is cleared.
+pub struct GlyphCache {
+    glyphs: HashMap<GlyphKey, Option<CachedGlyph>>,
+    generation: u32,
+}
+
+impl GlyphCache {
+    pub fn new() -> GlyphCache {
+        GlyphCache {
+            glyphs: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    //Err if the atlas is full.
+    fn get(
+        &mut self,
+        atlas: &mut TextureAtlas,
+        placed: &PlacedGlyph,
+        subpixel: u8,
+    ) -> Result<Option<CachedGlyph>, ()> {
+        if self.generation != atlas.generation() {
+            self.glyphs.clear();
+            self.generation = atlas.generation();
+        }
+
+        let key = GlyphKey {
+            font: placed.font,
+            id: placed.glyph.id(),
+            scale: placed.glyph.scale().y.to_bits(),
+            fake: placed.fake,
+            subpixel,
+        };
+        if let Some(&cached) = self.glyphs.get(&key) {
+            return Ok(cached);
+        }
+
+        let glyph = placed
+            .glyph
+            .unpositioned()
+            .clone()
+            .positioned(point(subpixel as f32 / SUBPIXEL_STEPS, 0.0));
+        let cached = match rasterize(&glyph, placed.fake, 0.0) {
+            Some((image, corner)) => Some(CachedGlyph {
+                region: atlas.insert(&image).ok_or(())?,
+                corner,
+            }),
+            None => None,
+        };
+        self.glyphs.insert(key, cached);
+        Ok(cached)
+    }
+}
+
+//Adds a box for each glyph, with the top left of the layout at `position` in the window.
+//Glyphs not in the atlas yet are drawn into it. Err if it is full, and then the atlas has to be cleared and everything added again.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_layout(
+    fonts: &Fonts,
+    layout: &TextLayout,
+    position: (f32, f32),
+    color: (f32, f32, f32),
+    cache: &mut GlyphCache,
+    atlas: &mut TextureAtlas,
+    window_size: (f32, f32),
+    verts: &mut Vec<GLfloat>,
+) -> Result<(), ()> {
+    for line in layout.lines.iter() {
+        let baseline = (position.1 + line.baseline).round();
+        let (glyphs, _) = place(
+            fonts,
+            layout.scale,
+            &line.chars,
+            position.0 + line.x,
+            baseline,
+        );
+        for placed in glyphs {
+            let x = placed.glyph.position().x;
+            let mut pixel = x.floor();
+            let mut subpixel = ((x - pixel) * SUBPIXEL_STEPS).round();
+            if subpixel >= SUBPIXEL_STEPS {
+                pixel += 1.0;
+                subpixel = 0.0;
+            }
+
+            let cached = match cache.get(atlas, &placed, subpixel as u8)? {
+                Some(cached) => cached,
+                None => continue,
+            };
+            let region = cached.region;
+            add_textured_box(
+                (
+                    pixel + cached.corner.0 as f32,
+                    baseline + cached.corner.1 as f32,
+                ),
+                0.0,
+                region.offset(),
+                color,
+                (region.width as f32, region.height as f32),
+                window_size,
+                atlas.size(),
+                verts,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fonts() -> Fonts {
+        let font = Font::from_bytes(&include_bytes!("../resources/Ubuntu-R.ttf")[..]).unwrap();
+        Fonts::new(vec![font], Vec::new(), Vec::new())
+    }
+
+    fn plain(text: &str) -> Vec<StyledChar> {
+        text.chars().map(|c| (c, Style::default())).collect()
+    }
+
+    fn text(line: &[StyledChar]) -> String {
+        line.iter().map(|&(c, _)| c).collect()
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces_and_inside_long_words() {
+        let fonts = fonts();
+        let scale = Scale::uniform(20.0);
+        let max_width = text_width(&fonts, scale, &plain("quick brown"));
+
+        let lines = wrap(&fonts, scale, &plain("the quick brown fox"), max_width);
+        let lines: Vec<String> = lines.iter().map(|l| text(l)).collect();
+        assert_eq!(lines, vec!["the quick", "brown fox"]);
+
+        let lines = wrap(&fonts, scale, &plain("a supercalifragilistic b"), max_width);
+        assert!(lines.len() >= 3);
+        assert_eq!(text(&lines[0]), "a");
+        for line in lines.iter() {
+            assert!(text_width(&fonts, scale, line) <= max_width);
+        }
+        //Nothing is dropped where a word is broken.
+        let rejoined: String = lines[1..].iter().map(|l| text(l)).collect();
+        assert_eq!(rejoined, "supercalifragilistic b");
+    }
+
+    #[test]
+    fn layout_starts_lines_at_breaks_and_aligns_them() {
+        let fonts = fonts();
+        let layout = layout_chars(
+            &fonts,
+            20.0,
+            &plain("Score\n3 of 10"),
+            None,
+            Alignment::Right,
+        );
+        assert_eq!(layout.lines.len(), 2);
+        assert!(layout.lines[1].baseline > layout.lines[0].baseline);
+
+        let widest = layout.lines.iter().map(|l| l.width).fold(0.0, f32::max);
+        for line in layout.lines.iter() {
+            assert_eq!(line.x, line.x.floor());
+            assert!(line.x + line.width <= PADDING + widest);
+            assert!(line.x + line.width > PADDING + widest - 1.0);
+        }
+        assert_eq!(layout.width, widest.ceil() as u32 + 2 * PADDING as u32);
+
+        let layout = layout_chars(
+            &fonts,
+            20.0,
+            &plain("Score\n3 of 10"),
+            None,
+            Alignment::Left,
+        );
+        assert!(layout.lines.iter().all(|l| l.x == PADDING));
+        let empty = layout_chars(&fonts, 20.0, &plain(""), None, Alignment::Center);
+        assert_eq!(empty.lines.len(), 1);
+        assert!(empty.width > 0 && empty.height > 0);
+    }
+}